@@ -0,0 +1,450 @@
+//! Runner for NIST ACVP ML-KEM vector sets.
+//!
+//! [`respond`] computes an ACVP response for a `keyGen` or `encapDecap`
+//! prompt, covering AFT and VAL groups for every parameter set, and
+//! [`validate`] compares a response with the server's `expectedResults.json`
+//! to produce a `validation.json`-style disposition per `tcId`. [`generate`]
+//! produces new vector sets in the same format from a seed.
+
+use std::collections::HashMap;
+
+use rand::{rngs::StdRng, RngCore, SeedableRng};
+use serde_json::{json, Map, Value};
+
+use crate::{self_test, MLKem, Type};
+
+pub const PARAMETER_SETS: [&str; 3] = ["ML-KEM-512", "ML-KEM-768", "ML-KEM-1024"];
+
+pub fn parameter_set(name: &str) -> Result<MLKem, String> {
+    match name {
+        "ML-KEM-512" => Ok(MLKem::new(Type::MlKem512)),
+        "ML-KEM-768" => Ok(MLKem::new(Type::MlKem768)),
+        "ML-KEM-1024" => Ok(MLKem::new(Type::MlKem1024)),
+        _ => Err(format!("unsupported parameter set {}", name)),
+    }
+}
+
+/// Computes the response to a prompt. The response is wrapped in the ACVP
+/// array format, `[{"acvVersion": ...}, {...}]`.
+pub fn respond(prompt: &Value) -> Result<Value, String> {
+    self_test::check_state()?;
+    let prompt = vector_set(prompt)?;
+    let mode = string(prompt, "mode")?;
+
+    let mut groups = vec![];
+    for group in array(prompt, "testGroups")? {
+        let ml_kem = parameter_set(string(group, "parameterSet")?)?;
+        let function = group.get("function").and_then(Value::as_str);
+        let mut tests = vec![];
+        for test in array(group, "tests")? {
+            let tc_id = field(test, "tcId")?;
+            let mut result = respond_to_test(&ml_kem, mode, function, group, test)
+                .map_err(|error| format!("tcId {}: {}", tc_id, error))?;
+            result.insert(String::from("tcId"), tc_id.clone());
+            tests.push(Value::Object(result));
+        }
+        groups.push(json!({ "tgId": field(group, "tgId")?, "tests": tests }));
+    }
+
+    Ok(json!([
+        { "acvVersion": "1.0" },
+        {
+            "vsId": field(prompt, "vsId")?,
+            "algorithm": field(prompt, "algorithm")?,
+            "mode": mode,
+            "revision": field(prompt, "revision")?,
+            "testGroups": groups,
+        }
+    ]))
+}
+
+fn respond_to_test(
+    ml_kem: &MLKem,
+    mode: &str,
+    function: Option<&str>,
+    group: &Value,
+    test: &Value,
+) -> Result<Map<String, Value>, String> {
+    let mut result = Map::new();
+    match (mode, function) {
+        ("keyGen", _) => {
+            let d = bytes(test, "d")?;
+            let z = bytes(test, "z")?;
+            let (ek, dk) = ml_kem._keygen_internal(&d, &z)?;
+            result.insert(String::from("ek"), hex_value(&ek));
+            result.insert(String::from("dk"), hex_value(&dk));
+        }
+        ("encapDecap", Some("encapsulation")) => {
+            let ek = bytes(test, "ek")?;
+            let m = bytes(test, "m")?;
+            let (k, c) = ml_kem._encaps_internal(&ek, &m)?;
+            result.insert(String::from("c"), hex_value(&c));
+            result.insert(String::from("k"), hex_value(&k));
+        }
+        ("encapDecap", Some("decapsulation")) => {
+            let dk = bytes(test, "dk").or_else(|_| bytes(group, "dk"))?;
+            let c = bytes(test, "c")?;
+            let k = ml_kem._decaps_internal(&dk, &c)?;
+            result.insert(String::from("k"), hex_value(&k));
+        }
+        ("encapDecap", Some("encapsulationKeyCheck")) => {
+            let ek = bytes(test, "ek")?;
+            let passed = ml_kem._check_encapsulation_key(&ek).is_ok();
+            result.insert(String::from("testPassed"), Value::Bool(passed));
+        }
+        ("encapDecap", Some("decapsulationKeyCheck")) => {
+            let dk = bytes(test, "dk")?;
+            let passed = ml_kem._check_decapsulation_key(&dk).is_ok();
+            result.insert(String::from("testPassed"), Value::Bool(passed));
+        }
+        _ => {
+            return Err(format!(
+                "unsupported mode {} and function {}",
+                mode,
+                function.unwrap_or("none")
+            ))
+        }
+    }
+    Ok(result)
+}
+
+/// Compares a response with the expected results. Every field of an expected
+/// test must be present and equal in the response for the test to pass.
+pub fn validate(response: &Value, expected: &Value) -> Result<Value, String> {
+    let response = vector_set(response)?;
+    let expected = vector_set(expected)?;
+
+    let mut responses = HashMap::new();
+    for group in array(response, "testGroups")? {
+        for test in array(group, "tests")? {
+            responses.insert(field(test, "tcId")?.to_string(), test);
+        }
+    }
+
+    let mut all_passed = true;
+    let mut tests = vec![];
+    for group in array(expected, "testGroups")? {
+        for test in array(group, "tests")? {
+            let tc_id = field(test, "tcId")?;
+            let mismatches = match responses.get(&tc_id.to_string()) {
+                Some(actual) => mismatched_fields(test, actual)?,
+                None => vec![String::from("missing test case")],
+            };
+            if mismatches.is_empty() {
+                tests.push(json!({ "tcId": tc_id, "result": "passed" }));
+            } else {
+                all_passed = false;
+                tests.push(json!({
+                    "tcId": tc_id,
+                    "result": "failed",
+                    "reason": format!("mismatch in {}", mismatches.join(", ")),
+                }));
+            }
+        }
+    }
+
+    Ok(json!({
+        "vsId": field(expected, "vsId")?,
+        "disposition": if all_passed { "passed" } else { "failed" },
+        "tests": tests,
+    }))
+}
+
+/// The three files of a generated vector set.
+pub struct VectorSet {
+    pub prompt: Value,
+    pub expected_results: Value,
+    pub internal_projection: Value,
+}
+
+/// Generates a `keyGen` or `encapDecap` vector set with `count` tests per group
+/// for each of `parameter_sets`, deterministically from `seed`.
+///
+/// `encapDecap` sets hold an AFT encapsulation group and a VAL decapsulation
+/// group per parameter set. Every other decapsulation test has one bit of its
+/// ciphertext flipped, so that its expected key is the implicit rejection key.
+pub fn generate(
+    mode: &str,
+    parameter_sets: &[&str],
+    count: usize,
+    seed: u64,
+) -> Result<VectorSet, String> {
+    self_test::check_state()?;
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut prompt_groups = vec![];
+    let mut expected_groups = vec![];
+    let mut projection_groups = vec![];
+    let mut tc_id = 1;
+
+    let functions: &[&str] = match mode {
+        "keyGen" => &["keyGen"],
+        "encapDecap" => &["encapsulation", "decapsulation"],
+        _ => return Err(format!("unsupported mode {}", mode)),
+    };
+    for &function in functions {
+        for &name in parameter_sets {
+            let ml_kem = parameter_set(name)?;
+            let tg_id = prompt_groups.len() + 1;
+            let mut group = Map::new();
+            group.insert(String::from("tgId"), Value::from(tg_id));
+            let test_type = if function == "decapsulation" {
+                "VAL"
+            } else {
+                "AFT"
+            };
+            group.insert(String::from("testType"), Value::from(test_type));
+            group.insert(String::from("parameterSet"), Value::from(name));
+            if mode == "encapDecap" {
+                group.insert(String::from("function"), Value::from(function));
+            }
+            let mut prompt_group = group.clone();
+            let mut projection_group = group;
+
+            let mut prompt_tests = vec![];
+            let mut expected_tests = vec![];
+            let mut projection_tests = vec![];
+            let group_keys = if function == "decapsulation" {
+                let (ek, dk) =
+                    ml_kem._keygen_internal(&random(&mut rng, 32), &random(&mut rng, 32))?;
+                prompt_group.insert(String::from("dk"), hex_value(&dk));
+                projection_group.insert(String::from("ek"), hex_value(&ek));
+                projection_group.insert(String::from("dk"), hex_value(&dk));
+                Some((ek, dk))
+            } else {
+                None
+            };
+            for i in 0..count {
+                let (prompt, expected, projection) = match function {
+                    "keyGen" => generate_key_gen(&ml_kem, &mut rng)?,
+                    "encapsulation" => generate_encapsulation(&ml_kem, &mut rng)?,
+                    _ => {
+                        let keys = group_keys.as_ref().unwrap();
+                        generate_decapsulation(&ml_kem, keys, i % 2 == 1, &mut rng)?
+                    }
+                };
+                for (tests, mut test) in [
+                    (&mut prompt_tests, prompt),
+                    (&mut expected_tests, expected),
+                    (&mut projection_tests, projection),
+                ] {
+                    test.insert(String::from("tcId"), Value::from(tc_id));
+                    tests.push(Value::Object(test));
+                }
+                tc_id += 1;
+            }
+
+            prompt_group.insert(String::from("tests"), Value::from(prompt_tests));
+            projection_group.insert(String::from("tests"), Value::from(projection_tests));
+            prompt_groups.push(Value::Object(prompt_group));
+            expected_groups.push(json!({ "tgId": tg_id, "tests": expected_tests }));
+            projection_groups.push(Value::Object(projection_group));
+        }
+    }
+
+    let vector_set = |groups: Vec<Value>| {
+        json!({
+            "vsId": 0,
+            "algorithm": "ML-KEM",
+            "mode": mode,
+            "revision": "FIPS203",
+            "isSample": false,
+            "testGroups": groups,
+        })
+    };
+    Ok(VectorSet {
+        prompt: vector_set(prompt_groups),
+        expected_results: vector_set(expected_groups),
+        internal_projection: vector_set(projection_groups),
+    })
+}
+
+type GeneratedTest = (Map<String, Value>, Map<String, Value>, Map<String, Value>);
+
+fn generate_key_gen(ml_kem: &MLKem, rng: &mut StdRng) -> Result<GeneratedTest, String> {
+    let z = random(rng, 32);
+    let d = random(rng, 32);
+    let (ek, dk) = ml_kem._keygen_internal(&d, &z)?;
+    let prompt = object(&[("z", hex_value(&z)), ("d", hex_value(&d))]);
+    let expected = object(&[("ek", hex_value(&ek)), ("dk", hex_value(&dk))]);
+    let mut projection = object(&[("deferred", Value::Bool(false))]);
+    projection.extend(prompt.clone());
+    projection.extend(expected.clone());
+    Ok((prompt, expected, projection))
+}
+
+fn generate_encapsulation(ml_kem: &MLKem, rng: &mut StdRng) -> Result<GeneratedTest, String> {
+    let (ek, dk) = ml_kem._keygen_internal(&random(rng, 32), &random(rng, 32))?;
+    let m = random(rng, 32);
+    let (k, c) = ml_kem._encaps_internal(&ek, &m)?;
+    let prompt = object(&[("ek", hex_value(&ek)), ("m", hex_value(&m))]);
+    let expected = object(&[("c", hex_value(&c)), ("k", hex_value(&k))]);
+    let projection = object(&[
+        ("deferred", Value::Bool(false)),
+        ("ek", hex_value(&ek)),
+        ("dk", hex_value(&dk)),
+        ("c", hex_value(&c)),
+        ("k", hex_value(&k)),
+        ("m", hex_value(&m)),
+        ("reason", Value::from("no modification")),
+    ]);
+    Ok((prompt, expected, projection))
+}
+
+fn generate_decapsulation(
+    ml_kem: &MLKem,
+    (ek, dk): &(Vec<u8>, Vec<u8>),
+    modify: bool,
+    rng: &mut StdRng,
+) -> Result<GeneratedTest, String> {
+    let (_, mut c) = ml_kem._encaps_internal(ek, &random(rng, 32))?;
+    let reason = if modify {
+        let bit = rng.next_u32() as usize % (8 * c.len());
+        c[bit / 8] ^= 1 << (bit % 8);
+        "modify ciphertext"
+    } else {
+        "no modification"
+    };
+    let k = ml_kem._decaps_internal(dk, &c)?;
+    let prompt = object(&[("c", hex_value(&c))]);
+    let expected = object(&[("k", hex_value(&k))]);
+    let projection = object(&[
+        ("deferred", Value::Bool(false)),
+        ("c", hex_value(&c)),
+        ("k", hex_value(&k)),
+        ("reason", Value::from(reason)),
+    ]);
+    Ok((prompt, expected, projection))
+}
+
+fn object(fields: &[(&str, Value)]) -> Map<String, Value> {
+    fields
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.clone()))
+        .collect()
+}
+
+fn random(rng: &mut StdRng, length: usize) -> Vec<u8> {
+    let mut bytes = vec![0; length];
+    rng.fill_bytes(&mut bytes);
+    bytes
+}
+
+fn mismatched_fields(expected: &Value, actual: &Value) -> Result<Vec<String>, String> {
+    let expected = expected
+        .as_object()
+        .ok_or_else(|| String::from("test case is not an object"))?;
+    let mut mismatches = vec![];
+    for (name, value) in expected {
+        let matches = match (value, actual.get(name)) {
+            (Value::String(x), Some(Value::String(y))) => x.eq_ignore_ascii_case(y),
+            (x, Some(y)) => x == y,
+            (_, None) => false,
+        };
+        if !matches {
+            mismatches.push(name.clone());
+        }
+    }
+    Ok(mismatches)
+}
+
+// Vector sets are served either bare or as `[{"acvVersion": ...}, {...}]`.
+fn vector_set(json: &Value) -> Result<&Value, String> {
+    match json {
+        Value::Array(elements) => elements
+            .iter()
+            .find(|element| element.get("testGroups").is_some())
+            .ok_or_else(|| String::from("no vector set found")),
+        _ => Ok(json),
+    }
+}
+
+fn field<'a>(object: &'a Value, name: &str) -> Result<&'a Value, String> {
+    object
+        .get(name)
+        .ok_or_else(|| format!("missing field {}", name))
+}
+
+fn string<'a>(object: &'a Value, name: &str) -> Result<&'a str, String> {
+    field(object, name)?
+        .as_str()
+        .ok_or_else(|| format!("field {} is not a string", name))
+}
+
+fn array<'a>(object: &'a Value, name: &str) -> Result<&'a Vec<Value>, String> {
+    field(object, name)?
+        .as_array()
+        .ok_or_else(|| format!("field {} is not an array", name))
+}
+
+fn bytes(object: &Value, name: &str) -> Result<Vec<u8>, String> {
+    hex::decode(string(object, name)?).map_err(|error| format!("field {}: {}", name, error))
+}
+
+fn hex_value(bytes: &[u8]) -> Value {
+    Value::String(hex::encode_upper(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+    use std::fs;
+
+    use super::{generate, respond, validate};
+    use crate::ML_KEM_512;
+
+    fn load(path: &str) -> Value {
+        serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    fn check_vector_set(directory: &str) {
+        let prompt = load(&format!("{}/prompt.json", directory));
+        let expected = load(&format!("{}/expectedResults.json", directory));
+        let validation = load(&format!("{}/validation.json", directory));
+
+        let response = respond(&prompt).unwrap();
+        let actual = validate(&response, &expected).unwrap();
+        assert_eq!(actual, validation);
+
+        let mut tampered = response.clone();
+        tampered[1]["testGroups"][0]["tests"][0]["tcId"] = Value::from(0);
+        let actual = validate(&tampered, &expected).unwrap();
+        assert_eq!(actual["disposition"], "failed");
+        assert_eq!(actual["tests"][0]["result"], "failed");
+        assert_eq!(actual["tests"][1]["result"], "passed");
+    }
+
+    #[test]
+    fn key_gen_vector_set() {
+        check_vector_set("assets/ML-KEM-keyGen-FIPS203");
+    }
+
+    #[test]
+    fn encap_decap_vector_set() {
+        check_vector_set("assets/ML-KEM-encapDecap-FIPS203");
+    }
+
+    #[test]
+    fn generated_vector_sets() {
+        for mode in ["keyGen", "encapDecap"] {
+            let parameter_sets = ["ML-KEM-512", "ML-KEM-768", "ML-KEM-1024"];
+            let vector_set = generate(mode, &parameter_sets, 4, 1).unwrap();
+            let response = respond(&vector_set.prompt).unwrap();
+            let validation = validate(&response, &vector_set.expected_results).unwrap();
+            assert_eq!(validation["disposition"], "passed");
+
+            let again = generate(mode, &parameter_sets, 4, 1).unwrap();
+            assert_eq!(again.internal_projection, vector_set.internal_projection);
+        }
+
+        let vector_set = generate("encapDecap", &["ML-KEM-512"], 4, 1).unwrap();
+        let group = &vector_set.internal_projection["testGroups"][1];
+        assert_eq!(group["testType"], "VAL");
+        let dk = hex::decode(group["dk"].as_str().unwrap()).unwrap();
+        let z = &dk[dk.len() - 32..];
+        let tests = group["tests"].as_array().unwrap();
+        assert_eq!(tests[1]["reason"], "modify ciphertext");
+        let c = hex::decode(tests[1]["c"].as_str().unwrap()).unwrap();
+        let rejection = hex::encode_upper(ML_KEM_512._j(&[z, &c].concat()));
+        assert_eq!(tests[1]["k"], rejection.as_str());
+    }
+}
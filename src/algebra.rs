@@ -0,0 +1,28 @@
+//! Polynomial and module arithmetic over R_q = Z_q[X]/(X^n + 1), the algebra
+//! ML-KEM is built on, for reuse in other lattice-based constructions.
+//!
+//! [`Ring`] is a polynomial in the normal or the NTT domain, [`Module`] a
+//! matrix of polynomials and [`RingParams`] the choice of n, q and the root of
+//! unity of the NTT, `RingParams::ML_KEM` by default. Sampling, compression and
+//! encoding are the FIPS 203 algorithms. Adding or subtracting modules of
+//! different dimensions panics.
+//!
+//! ```
+//! use pqc_ml_kem::algebra::{Module, Ring, RingParams};
+//! use rand::{rngs::StdRng, SeedableRng};
+//!
+//! let mut rng = StdRng::seed_from_u64(1);
+//! let params = RingParams::ML_KEM;
+//! let a = Module::random_with_rng(params, &mut rng, 2, 2, true);
+//! let s = Module::random_with_rng(params, &mut rng, 2, 1, true);
+//! let t = a.mat_mul(&s).unwrap();
+//! assert_eq!(t.dim(), (2, 1));
+//! assert_eq!(&(&t - &t) + &t, t);
+//!
+//! let f = Ring::random_with_rng(params, &mut rng, false);
+//! assert_eq!(&f + &-&f, Ring::zero());
+//! assert_eq!(f.to_ntt().unwrap().from_ntt(), f);
+//! ```
+
+pub use crate::module::Module;
+pub use crate::ring::{Ring, RingParams};
@@ -0,0 +1,52 @@
+//! The Keccak-based functions ML-KEM is built from. `MLKem` calls them only
+//! through [`HashBackend`], so another implementation, such as a driver for a
+//! hardware Keccak accelerator or a wrapper that records every call, can be
+//! supplied with `MLKem::with_backend`.
+
+use sha3::{
+    digest::{ExtendableOutput, Update, XofReader},
+    Digest, Sha3_256, Sha3_512, Shake128, Shake128Reader, Shake256,
+};
+
+/// SHA3-256, SHA3-512, SHAKE128 and SHAKE256 as specified in FIPS 202.
+pub trait HashBackend {
+    /// The output stream of SHAKE128, squeezed incrementally by SampleNTT.
+    type Shake128Reader: XofReader;
+
+    fn sha3_256(&self, input: &[u8]) -> [u8; 32];
+
+    fn sha3_512(&self, input: &[u8]) -> [u8; 64];
+
+    fn shake128(&self, input: &[u8]) -> Self::Shake128Reader;
+
+    /// Fills `output` with the first `output.len()` bytes of SHAKE256.
+    fn shake256(&self, input: &[u8], output: &mut [u8]);
+}
+
+/// The default backend, the `sha3` crate.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Sha3Backend;
+
+impl HashBackend for Sha3Backend {
+    type Shake128Reader = Shake128Reader;
+
+    fn sha3_256(&self, input: &[u8]) -> [u8; 32] {
+        Sha3_256::digest(input).into()
+    }
+
+    fn sha3_512(&self, input: &[u8]) -> [u8; 64] {
+        Sha3_512::digest(input).into()
+    }
+
+    fn shake128(&self, input: &[u8]) -> Self::Shake128Reader {
+        let mut hasher = Shake128::default();
+        hasher.update(input);
+        hasher.finalize_xof()
+    }
+
+    fn shake256(&self, input: &[u8], output: &mut [u8]) {
+        let mut hasher = Shake256::default();
+        hasher.update(input);
+        hasher.finalize_xof().read(output);
+    }
+}
@@ -0,0 +1,87 @@
+use std::{env, fs, process};
+
+use pqc_ml_kem::acvp;
+use serde_json::Value;
+
+const USAGE: &str = "usage:
+    acvp run <prompt.json> <response.json>
+    acvp validate <response.json> <expectedResults.json> <validation.json>
+    acvp generate <keyGen|encapDecap> <parameterSet[,...]> <count> <seed> <directory>";
+
+fn read_json(path: &str) -> Result<Value, String> {
+    let data = fs::read_to_string(path).map_err(|error| format!("{}: {}", path, error))?;
+    serde_json::from_str(&data).map_err(|error| format!("{}: {}", path, error))
+}
+
+fn write_json(path: &str, value: &Value) -> Result<(), String> {
+    let data = serde_json::to_string_pretty(value).unwrap();
+    fs::write(path, data).map_err(|error| format!("{}: {}", path, error))
+}
+
+fn run(prompt: &str, response: &str) -> Result<(), String> {
+    let prompt = read_json(prompt)?;
+    write_json(response, &acvp::respond(&prompt)?)
+}
+
+fn generate(
+    mode: &str,
+    parameter_sets: &str,
+    count: &str,
+    seed: &str,
+    directory: &str,
+) -> Result<(), String> {
+    let parameter_sets: Vec<&str> = parameter_sets.split(',').collect();
+    let count = count.parse().map_err(|error| format!("count: {}", error))?;
+    let seed = seed.parse().map_err(|error| format!("seed: {}", error))?;
+    let vector_set = acvp::generate(mode, &parameter_sets, count, seed)?;
+
+    fs::create_dir_all(directory).map_err(|error| format!("{}: {}", directory, error))?;
+    let path = |name: &str| format!("{}/{}", directory, name);
+    write_json(&path("prompt.json"), &vector_set.prompt)?;
+    write_json(&path("expectedResults.json"), &vector_set.expected_results)?;
+    write_json(
+        &path("internalProjection.json"),
+        &vector_set.internal_projection,
+    )
+}
+
+fn validate(response: &str, expected: &str, validation: &str) -> Result<bool, String> {
+    let validation_json = acvp::validate(&read_json(response)?, &read_json(expected)?)?;
+    write_json(validation, &validation_json)?;
+    let tests = validation_json["tests"].as_array().unwrap();
+    let failed: Vec<&Value> = tests
+        .iter()
+        .filter(|test| test["result"] != "passed")
+        .collect();
+    for test in failed.iter() {
+        println!("tcId {}: {}", test["tcId"], test["reason"]);
+    }
+    println!(
+        "{}: {} of {} tests passed",
+        validation_json["disposition"],
+        tests.len() - failed.len(),
+        tests.len()
+    );
+    Ok(failed.is_empty())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let result = match args[1..] {
+        ["run", prompt, response] => run(prompt, response).map(|_| true),
+        ["validate", response, expected, validation] => validate(response, expected, validation),
+        ["generate", mode, parameter_sets, count, seed, directory] => {
+            generate(mode, parameter_sets, count, seed, directory).map(|_| true)
+        }
+        _ => Err(String::from(USAGE)),
+    };
+    match result {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use std::{env, process};
+
+use pqc_ml_kem::{
+    acvp, failure,
+    security::{self, AttackCost, Estimate, InstanceEstimate},
+};
+
+const USAGE: &str = "usage: analyze [<k> <eta_1> <eta_2> <du> <dv>]";
+
+fn print_attack(name: &str, cost: &AttackCost) {
+    println!(
+        "    {} attack: m = {}, beta = {}, core-SVP {:.1} classical, {:.1} quantum",
+        name, cost.samples, cost.block_size, cost.classical, cost.quantum
+    );
+}
+
+fn print_instance(name: &str, estimate: &InstanceEstimate) {
+    println!("  {} instance:", name);
+    print_attack("primal", &estimate.primal);
+    print_attack("dual", &estimate.dual);
+}
+
+fn print_analysis(name: &str, failure_probability: f64, estimate: &Estimate) {
+    println!("{}:", name);
+    println!(
+        "  decryption failure probability 2^{:.1}",
+        failure_probability.log2()
+    );
+    print_instance("key", &estimate.key);
+    print_instance("ciphertext", &estimate.ciphertext);
+    match estimate.category() {
+        Some(category) => println!("  NIST category {}", category),
+        None => println!("  below NIST category 1"),
+    }
+}
+
+fn standard() -> Result<(), String> {
+    for name in acvp::PARAMETER_SETS {
+        let ml_kem = acvp::parameter_set(name)?;
+        print_analysis(
+            name,
+            ml_kem.failure_probability()?,
+            &ml_kem.security_estimate()?,
+        );
+    }
+    Ok(())
+}
+
+fn custom(parameters: &[&str]) -> Result<(), String> {
+    let values = parameters
+        .iter()
+        .map(|value| {
+            value
+                .parse::<u8>()
+                .map_err(|error| format!("{}: {}", value, error))
+        })
+        .collect::<Result<Vec<u8>, String>>()?;
+    let (k, eta_1, eta_2, du, dv) = (values[0], values[1], values[2], values[3], values[4]);
+    let name = format!(
+        "k = {}, eta_1 = {}, eta_2 = {}, du = {}, dv = {}",
+        k, eta_1, eta_2, du, dv
+    );
+    print_analysis(
+        &name,
+        failure::failure_probability(k, eta_1, eta_2, du, dv)?,
+        &security::estimate(k, eta_1, eta_2, du, dv)?,
+    );
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let result = match args[1..] {
+        [] => standard(),
+        [_, _, _, _, _] => custom(&args[1..]),
+        _ => Err(String::from(USAGE)),
+    };
+    if let Err(error) = result {
+        eprintln!("{}", error);
+        process::exit(2);
+    }
+}
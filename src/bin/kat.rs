@@ -0,0 +1,33 @@
+use std::{env, fs, process};
+
+use pqc_ml_kem::{acvp, rsp};
+
+const USAGE: &str = "usage: kat <directory> [count]";
+
+fn run(directory: &str, count: usize) -> Result<(), String> {
+    fs::create_dir_all(directory).map_err(|error| format!("{}: {}", directory, error))?;
+    for name in acvp::PARAMETER_SETS {
+        let contents = rsp::generate(name, count)?;
+        let path = format!("{}/{}", directory, rsp::file_name(name)?);
+        fs::write(&path, &contents).map_err(|error| format!("{}: {}", path, error))?;
+        println!("{}  {}", rsp::sha256(&contents), path);
+    }
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let result = match args[1..] {
+        [directory] => run(directory, 100),
+        [directory, count] => count
+            .parse()
+            .map_err(|error| format!("count: {}", error))
+            .and_then(|count| run(directory, count)),
+        _ => Err(String::from(USAGE)),
+    };
+    if let Err(error) = result {
+        eprintln!("{}", error);
+        process::exit(2);
+    }
+}
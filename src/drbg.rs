@@ -0,0 +1,636 @@
+//! Hash_DRBG from NIST SP 800-90A with SHA-512, seeded from the operating
+//! system through the continuous health tests of NIST SP 800-90B.
+//!
+//! A thread-local instance is the default randomness source for `keygen` and
+//! `encaps`. Any [`HashDrbg`] can also be passed explicitly to
+//! `try_keygen_with_rng` and `try_encaps_with_rng`.
+//!
+//! [`AesCtrDrbg`] is the deterministic `randombytes` generator used to produce
+//! the NIST PQC `.rsp` known-answer files, and is only meant for reproducing
+//! them. It does not implement `CryptoRng`.
+
+use std::cell::RefCell;
+
+use aes::{
+    cipher::{BlockEncrypt, KeyInit},
+    Aes256,
+};
+use rand::{rngs::OsRng, CryptoRng, RngCore};
+use sha2::{Digest, Sha512};
+
+const SEED_LEN: usize = 111; // 888 bits for SHA-512
+const SECURITY_STRENGTH: usize = 256;
+const MAX_BYTES_PER_REQUEST: usize = 1 << 16;
+// SP 800-90A allows up to 2^48 requests between reseeds.
+const DEFAULT_RESEED_INTERVAL: u64 = 1 << 48;
+// Claimed min-entropy per byte of raw entropy input, in bits.
+const DEFAULT_MIN_ENTROPY: f64 = 4.0;
+// False positive probability of the health tests, as a power of two.
+const ALPHA_EXPONENT: f64 = 40.0;
+const APT_WINDOW: usize = 512;
+
+/// Repetition count and adaptive proportion tests from SP 800-90B 4.4, run on
+/// every byte of raw entropy input.
+#[derive(Clone, Debug)]
+pub struct HealthTests {
+    rct_cutoff: usize,
+    apt_cutoff: usize,
+    last: Option<u8>,
+    run: usize,
+    apt_first: u8,
+    apt_count: usize,
+    apt_seen: usize,
+}
+
+impl HealthTests {
+    /// Health tests for a source with the given claimed min-entropy per byte,
+    /// which must be in (0, 8].
+    pub fn new(min_entropy: f64) -> Result<Self, String> {
+        if !(min_entropy > 0.0 && min_entropy <= 8.0) {
+            return Err(format!(
+                "min-entropy {} is not in (0, 8] bits per byte",
+                min_entropy
+            ));
+        }
+        Ok(HealthTests {
+            rct_cutoff: 1 + (ALPHA_EXPONENT / min_entropy).ceil() as usize,
+            apt_cutoff: 1 + critical_binomial(APT_WINDOW, 2_f64.powf(-min_entropy)),
+            last: None,
+            run: 0,
+            apt_first: 0,
+            apt_count: 0,
+            apt_seen: 0,
+        })
+    }
+
+    pub fn check(&mut self, sample: u8) -> Result<(), String> {
+        if self.last == Some(sample) {
+            self.run += 1;
+        } else {
+            self.last = Some(sample);
+            self.run = 1;
+        }
+        if self.run >= self.rct_cutoff {
+            return Err(String::from(
+                "entropy health test failed: repetition count test",
+            ));
+        }
+
+        if self.apt_seen == 0 {
+            self.apt_first = sample;
+            self.apt_count = 1;
+        } else if sample == self.apt_first {
+            self.apt_count += 1;
+        }
+        self.apt_seen = (self.apt_seen + 1) % APT_WINDOW;
+        if self.apt_count >= self.apt_cutoff {
+            return Err(String::from(
+                "entropy health test failed: adaptive proportion test",
+            ));
+        }
+        Ok(())
+    }
+}
+
+// Smallest k with P(X <= k) >= 1 - alpha for X ~ Binomial(n, p).
+fn critical_binomial(n: usize, p: f64) -> usize {
+    let alpha = 2_f64.powf(-ALPHA_EXPONENT);
+    let mut ln_choose = 0.0;
+    let mut cdf = 0.0;
+    for k in 0..=n {
+        if k > 0 {
+            ln_choose += ((n - k + 1) as f64).ln() - (k as f64).ln();
+        }
+        cdf += (ln_choose + k as f64 * p.ln() + (n - k) as f64 * (1.0 - p).ln()).exp();
+        if cdf >= 1.0 - alpha {
+            return k;
+        }
+    }
+    n
+}
+
+pub struct HashDrbg<E: RngCore = OsRng> {
+    entropy_source: E,
+    health_tests: HealthTests,
+    min_entropy: f64,
+    v: [u8; SEED_LEN],
+    c: [u8; SEED_LEN],
+    reseed_counter: u64,
+    reseed_interval: u64,
+    failed: bool,
+}
+
+impl HashDrbg<OsRng> {
+    pub fn new() -> Result<Self, String> {
+        Self::from_entropy_source(OsRng, DEFAULT_MIN_ENTROPY, &[])
+    }
+}
+
+impl<E: RngCore> HashDrbg<E> {
+    /// Instantiates the DRBG from raw entropy with the given claimed
+    /// min-entropy per byte, in (0, 8]. The nonce is drawn from the same
+    /// source.
+    pub fn from_entropy_source(
+        entropy_source: E,
+        min_entropy: f64,
+        personalization: &[u8],
+    ) -> Result<Self, String> {
+        let mut drbg = HashDrbg {
+            entropy_source,
+            health_tests: HealthTests::new(min_entropy)?,
+            min_entropy,
+            v: [0; SEED_LEN],
+            c: [0; SEED_LEN],
+            reseed_counter: 0,
+            reseed_interval: DEFAULT_RESEED_INTERVAL,
+            failed: false,
+        };
+        let entropy_input = drbg._entropy_input(SECURITY_STRENGTH + SECURITY_STRENGTH / 2)?;
+        let seed_material = [entropy_input.as_slice(), personalization].concat();
+        drbg._update_seed(&seed_material);
+        Ok(drbg)
+    }
+
+    pub fn with_reseed_interval(mut self, reseed_interval: u64) -> Self {
+        self.reseed_interval = reseed_interval.clamp(1, DEFAULT_RESEED_INTERVAL);
+        self
+    }
+
+    pub fn reseed_counter(&self) -> u64 {
+        self.reseed_counter
+    }
+
+    pub fn reseed(&mut self, additional_input: &[u8]) -> Result<(), String> {
+        self._check_state()?;
+        let entropy_input = self._entropy_input(SECURITY_STRENGTH)?;
+        let seed_material = [&[0x01], &self.v[..], &entropy_input, additional_input].concat();
+        self._update_seed(&seed_material);
+        Ok(())
+    }
+
+    pub fn generate(&mut self, output: &mut [u8], additional_input: &[u8]) -> Result<(), String> {
+        self._check_state()?;
+        if output.len() > MAX_BYTES_PER_REQUEST {
+            return Err(String::from("DRBG request is too large"));
+        }
+        if self.reseed_counter > self.reseed_interval {
+            self.reseed(additional_input)?;
+        } else if !additional_input.is_empty() {
+            let w = hash(&[&[0x02], &self.v[..], additional_input]);
+            add_into(&mut self.v, &w);
+        }
+
+        let mut data = self.v;
+        for chunk in output.chunks_mut(64) {
+            let w = hash(&[&data]);
+            chunk.copy_from_slice(&w[..chunk.len()]);
+            add_into(&mut data, &[1]);
+        }
+
+        let h = hash(&[&[0x03], &self.v[..]]);
+        let c = self.c;
+        add_into(&mut self.v, &h);
+        add_into(&mut self.v, &c);
+        add_into(&mut self.v, &self.reseed_counter.to_be_bytes());
+        self.reseed_counter += 1;
+        Ok(())
+    }
+
+    fn _check_state(&self) -> Result<(), String> {
+        if self.failed {
+            return Err(String::from("DRBG is in the error state"));
+        }
+        Ok(())
+    }
+
+    fn _entropy_input(&mut self, bits: usize) -> Result<Vec<u8>, String> {
+        let length = (bits as f64 / self.min_entropy).ceil() as usize;
+        let mut entropy_input = vec![0_u8; length];
+        if let Err(error) = self.entropy_source.try_fill_bytes(&mut entropy_input) {
+            self.failed = true;
+            return Err(format!("entropy source failed: {}", error));
+        }
+        for &sample in entropy_input.iter() {
+            if let Err(error) = self.health_tests.check(sample) {
+                self.failed = true;
+                return Err(error);
+            }
+        }
+        Ok(entropy_input)
+    }
+
+    fn _update_seed(&mut self, seed_material: &[u8]) {
+        self.v = hash_df(seed_material);
+        self.c = hash_df(&[&[0x00], &self.v[..]].concat());
+        self.reseed_counter = 1;
+    }
+}
+
+impl<E: RngCore> RngCore for HashDrbg<E> {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0_u8; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0_u8; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.try_fill_bytes(dest).unwrap()
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        for chunk in dest.chunks_mut(MAX_BYTES_PER_REQUEST) {
+            self.generate(chunk, &[]).map_err(rand::Error::new)?;
+        }
+        Ok(())
+    }
+}
+
+impl<E: RngCore> CryptoRng for HashDrbg<E> {}
+
+/// The AES-256 CTR_DRBG without derivation function and without reseeding
+/// from `rng.c` of the NIST PQC submission package.
+pub struct AesCtrDrbg {
+    key: [u8; 32],
+    v: [u8; 16],
+    reseed_counter: u64,
+}
+
+impl AesCtrDrbg {
+    pub fn new(entropy_input: &[u8; 48], personalization: Option<&[u8; 48]>) -> Self {
+        let mut seed_material = *entropy_input;
+        if let Some(personalization) = personalization {
+            for (x, y) in seed_material.iter_mut().zip(personalization) {
+                *x ^= y;
+            }
+        }
+        let mut drbg = AesCtrDrbg {
+            key: [0; 32],
+            v: [0; 16],
+            reseed_counter: 1,
+        };
+        drbg._update(Some(&seed_material));
+        drbg
+    }
+
+    pub fn reseed_counter(&self) -> u64 {
+        self.reseed_counter
+    }
+
+    // Each call produces whole blocks and discards the unused bytes of the last
+    // one, so splitting a request changes the output.
+    fn _randombytes(&mut self, output: &mut [u8]) {
+        for chunk in output.chunks_mut(16) {
+            let block = self._next_block();
+            chunk.copy_from_slice(&block[..chunk.len()]);
+        }
+        self._update(None);
+        self.reseed_counter += 1;
+    }
+
+    fn _update(&mut self, provided_data: Option<&[u8; 48]>) {
+        let mut temp = [0_u8; 48];
+        for chunk in temp.chunks_mut(16) {
+            chunk.copy_from_slice(&self._next_block());
+        }
+        if let Some(provided_data) = provided_data {
+            for (x, y) in temp.iter_mut().zip(provided_data) {
+                *x ^= y;
+            }
+        }
+        self.key.copy_from_slice(&temp[..32]);
+        self.v.copy_from_slice(&temp[32..]);
+    }
+
+    fn _next_block(&mut self) -> [u8; 16] {
+        for byte in self.v.iter_mut().rev() {
+            *byte = byte.wrapping_add(1);
+            if *byte != 0 {
+                break;
+            }
+        }
+        let mut block = self.v.into();
+        Aes256::new(&self.key.into()).encrypt_block(&mut block);
+        block.into()
+    }
+}
+
+impl RngCore for AesCtrDrbg {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0_u8; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0_u8; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self._randombytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self._randombytes(dest);
+        Ok(())
+    }
+}
+
+// The default DRBG and the id of the process that instantiated it. A forked
+// child inherits a copy of the parent's state, so it instantiates a fresh
+// DRBG from the operating system instead of repeating the parent's output.
+thread_local! {
+    static DEFAULT_DRBG: RefCell<Option<(u32, HashDrbg)>> = const { RefCell::new(None) };
+}
+
+pub(crate) fn with_default<T>(
+    f: impl FnOnce(&mut HashDrbg) -> Result<T, String>,
+) -> Result<T, String> {
+    _with_default(std::process::id(), f)
+}
+
+fn _with_default<T>(
+    pid: u32,
+    f: impl FnOnce(&mut HashDrbg) -> Result<T, String>,
+) -> Result<T, String> {
+    DEFAULT_DRBG.with(|drbg| {
+        let mut drbg = drbg.borrow_mut();
+        if !matches!(*drbg, Some((owner, _)) if owner == pid) {
+            *drbg = Some((pid, HashDrbg::new()?));
+        }
+        f(&mut drbg.as_mut().unwrap().1)
+    })
+}
+
+fn hash(inputs: &[&[u8]]) -> [u8; 64] {
+    let mut hasher = Sha512::new();
+    for input in inputs {
+        hasher.update(input);
+    }
+    hasher.finalize().into()
+}
+
+fn hash_df(input: &[u8]) -> [u8; SEED_LEN] {
+    let bits = (8 * SEED_LEN as u32).to_be_bytes();
+    let mut output = [0_u8; SEED_LEN];
+    for (i, chunk) in output.chunks_mut(64).enumerate() {
+        let counter = [i as u8 + 1];
+        let block = hash(&[&counter, &bits, input]);
+        chunk.copy_from_slice(&block[..chunk.len()]);
+    }
+    output
+}
+
+// v = (v + x) mod 2^seedlen, both big-endian.
+fn add_into(v: &mut [u8; SEED_LEN], x: &[u8]) {
+    let mut carry = 0_u16;
+    let mut x = x.iter().rev();
+    for byte in v.iter_mut().rev() {
+        let sum = *byte as u16 + *x.next().unwrap_or(&0) as u16 + carry;
+        *byte = sum as u8;
+        carry = sum >> 8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::mock::StepRng, rngs::StdRng, RngCore, SeedableRng};
+
+    use super::{_with_default, with_default, AesCtrDrbg, HashDrbg, HealthTests};
+    use crate::ML_KEM_512;
+
+    #[test]
+    fn deterministic_for_fixed_entropy() {
+        let mut drbg_1 =
+            HashDrbg::from_entropy_source(StdRng::seed_from_u64(1), 8.0, b"ml-kem").unwrap();
+        let mut drbg_2 =
+            HashDrbg::from_entropy_source(StdRng::seed_from_u64(1), 8.0, b"ml-kem").unwrap();
+        let mut drbg_3 =
+            HashDrbg::from_entropy_source(StdRng::seed_from_u64(1), 8.0, b"other").unwrap();
+
+        let mut output = [[0_u8; 100]; 3];
+        drbg_1.fill_bytes(&mut output[0]);
+        drbg_2.fill_bytes(&mut output[1]);
+        drbg_3.fill_bytes(&mut output[2]);
+        assert_eq!(output[0], output[1]);
+        assert_ne!(output[0], output[2]);
+
+        drbg_1.fill_bytes(&mut output[0]);
+        assert_ne!(output[0], output[1]);
+    }
+
+    // Replays fixed entropy input, nonce and reseed entropy input.
+    struct Replay(std::vec::IntoIter<u8>);
+
+    impl RngCore for Replay {
+        fn next_u32(&mut self) -> u32 {
+            let mut bytes = [0_u8; 4];
+            self.fill_bytes(&mut bytes);
+            u32::from_le_bytes(bytes)
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            let mut bytes = [0_u8; 8];
+            self.fill_bytes(&mut bytes);
+            u64::from_le_bytes(bytes)
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            for byte in dest.iter_mut() {
+                *byte = self.0.next().unwrap();
+            }
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    // COUNT = 0 of the SHA-512 groups without and with personalization string
+    // and additional input in Hash_DRBG.rsp of the CAVP drbgvectors_pr_false
+    // set: EntropyInput, Nonce, PersonalizationString, EntropyInputReseed,
+    // AdditionalInputReseed, the two AdditionalInputs and ReturnedBits.
+    const CAVP_VECTORS: [[&str; 8]; 2] = [
+        [
+            "3144e17a10c856129764f58fd8e4231020546996c0bf6cff8e91c24ee09be333",
+            "b16fcb1cf0c010f31feab733588b8e04",
+            "",
+            "a0b3584c2c8412f618406834404d1eb0ce999ba28966054d7e497e0db608b967",
+            "",
+            "",
+            "",
+            "efa35dd0362adb7626456b36fac74d3c28d01d926420275a28bea9c9dd7547c15e7931852ac1277076567535239c1f429c7f75cf74c2267deb6a3e596cf326156c796941283b8d583f171c2f6e3323f7555e1b181ffda30507210cb1f589b23cd71880fd44370cacf43375b0db7e336f12b309bfd4f610bb8f20e1a15e253a4fe511a027968df0b105a1d73aff7c7a826d39f640dfb8f522259ed402282e2c2e9d3a498f51725fe4141b06da5598a42ac1e0494e997d566a1a39b676b96a6003a4c5db84f246584ee65af70ff2160278166da16d91c9b8f2deb02751a1088ad6be4e80ef966eb73e66bc87cad87c77c0b34a21ba1da0ba6d16ca5046dc4abda0",
+        ],
+        [
+            "4b23595b0a3640cfabb0ec34df6a613308b0448488a5d9ff99da4278e072eb34",
+            "8e696bffd9ca3a71d2e2f05e600c8364",
+            "010ba93ea68a3d4a200e5145859e299c5b5349b7645fb5bbcad687aba7d67313",
+            "04de4babdbe143bde99aa4452f9aa43b0a164eb927555c0496aa0fc9328a521c",
+            "2b0c7c3efb36b71b917a44086d168313675b426b17c5ab3d0eb6af753f6040e0",
+            "d0b7d1d12ab15d3bba8f4eba07fee0974838962b247be480683b8e3d4a91033a",
+            "66c78ca12e45bdca003b49cb6440b977dd85b167e7c803890ed1a73666eaa869",
+            "4008cbd8281dc82fd6c368f650ef2609bb771e80c63d478a77fa938248dcbb8b79e54ead0265f6ff1ebfafe4e387c6e27df9f03e4a5225e86a4436e56ebf03b3be2cfbcb49c89c92ec1dfa5ee445dd4f6f64e02a2423a0b18ebd02eec52f5cc21bc3565e796b3ded6552f1b5a574a201c3b11018222806f9618d23d77fd02db879cf87fe24ed7ba11b3b108b559633db1f95c5121b28011aa4dd20399bd4978e1f8b8880c333a47ff1750679bf28d329347b26d347aae90ee562ae8029579cbe0336e066d6b8ba5e0169fec804c30189a4434c1bf8a5b0a249951d3d89554da38ff0751b8b1fef9ae18a0aa2bc477736d199a06f61d400039a4cc03869bb10ca",
+        ],
+    ];
+
+    #[test]
+    fn hash_drbg_cavp() {
+        for vector in CAVP_VECTORS {
+            let vector = vector.map(|x| hex::decode(x).unwrap());
+            // With a claimed min-entropy of 8 bits per byte, instantiation
+            // draws exactly the 32-byte entropy input and 16-byte nonce.
+            let source = Replay(
+                [&vector[0][..], &vector[1], &vector[3]]
+                    .concat()
+                    .into_iter(),
+            );
+            let mut drbg = HashDrbg::from_entropy_source(source, 8.0, &vector[2]).unwrap();
+            drbg.reseed(&vector[4]).unwrap();
+            let mut output = [0_u8; 256];
+            drbg.generate(&mut output, &vector[5]).unwrap();
+            drbg.generate(&mut output, &vector[6]).unwrap();
+            assert_eq!(output.as_slice(), vector[7]);
+        }
+    }
+
+    #[test]
+    fn reseeds_after_interval() {
+        let mut drbg = HashDrbg::from_entropy_source(StdRng::seed_from_u64(2), 8.0, &[])
+            .unwrap()
+            .with_reseed_interval(3);
+        let mut output = [0_u8; 32];
+        for _ in 0..3 {
+            drbg.generate(&mut output, &[]).unwrap();
+        }
+        assert_eq!(drbg.reseed_counter(), 4);
+        drbg.generate(&mut output, &[]).unwrap();
+        assert_eq!(drbg.reseed_counter(), 2);
+    }
+
+    #[test]
+    fn health_tests_reject_bad_entropy() {
+        let stuck = HashDrbg::from_entropy_source(StepRng::new(0, 0), 4.0, &[]);
+        assert_eq!(
+            stuck.err().unwrap(),
+            "entropy health test failed: repetition count test"
+        );
+
+        let mut health_tests = HealthTests::new(4.0).unwrap();
+        let mut result = Ok(());
+        for i in 0..512 {
+            result = result.and(health_tests.check((i % 3) as u8));
+        }
+        assert_eq!(
+            result.unwrap_err(),
+            "entropy health test failed: adaptive proportion test"
+        );
+
+        let mut health_tests = HealthTests::new(4.0).unwrap();
+        let mut rng = StdRng::seed_from_u64(3);
+        for _ in 0..100_000 {
+            health_tests.check(rng.next_u32() as u8).unwrap();
+        }
+    }
+
+    #[test]
+    fn min_entropy_is_checked() {
+        for min_entropy in [0.0, -1.0, 8.5, f64::NAN] {
+            assert!(HealthTests::new(min_entropy).is_err());
+            let drbg = HashDrbg::from_entropy_source(StdRng::seed_from_u64(5), min_entropy, &[]);
+            assert!(drbg.is_err());
+        }
+        assert!(HealthTests::new(0.5).is_ok());
+        assert!(HealthTests::new(8.0).is_ok());
+    }
+
+    #[test]
+    fn default_is_reinstantiated_after_fork() {
+        let pid = std::process::id();
+        let mut output = [[0_u8; 32]; 2];
+        with_default(|drbg| drbg.generate(&mut output[0], &[])).unwrap();
+        let counter = _with_default(pid, |drbg| Ok(drbg.reseed_counter())).unwrap();
+        assert!(counter > 1);
+
+        // A different process id stands in for a forked child.
+        let counter = _with_default(pid.wrapping_add(1), |drbg| {
+            drbg.generate(&mut output[1], &[])?;
+            Ok(drbg.reseed_counter())
+        })
+        .unwrap();
+        assert_eq!(counter, 2);
+        assert_ne!(output[0], output[1]);
+    }
+
+    // Yields good entropy for the first `remaining` bytes and zeros after.
+    struct StuckAfter {
+        remaining: usize,
+        rng: StdRng,
+    }
+
+    impl RngCore for StuckAfter {
+        fn next_u32(&mut self) -> u32 {
+            self.rng.next_u32()
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            self.rng.next_u64()
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            for byte in dest.iter_mut() {
+                *byte = if self.remaining > 0 {
+                    self.remaining -= 1;
+                    self.rng.next_u32() as u8
+                } else {
+                    0
+                };
+            }
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn keygen_reports_drbg_failure() {
+        let source = StuckAfter {
+            remaining: 96,
+            rng: StdRng::seed_from_u64(4),
+        };
+        let mut drbg = HashDrbg::from_entropy_source(source, 4.0, &[])
+            .unwrap()
+            .with_reseed_interval(1);
+        // The first request is served from the instantiation entropy and the
+        // second one reseeds from the stuck source.
+        ML_KEM_512.try_keygen_with_rng(&mut drbg).unwrap();
+        let error = ML_KEM_512.try_keygen_with_rng(&mut drbg).unwrap_err();
+        assert!(error.contains("repetition count test"));
+        assert!(ML_KEM_512.try_keygen_with_rng(&mut drbg).is_err());
+        assert!(ML_KEM_512
+            .try_encaps_with_rng(&[0; 800], &mut drbg)
+            .is_err());
+    }
+
+    #[test]
+    fn aes_ctr_drbg_nist_seed() {
+        let entropy_input: Vec<u8> = (0..48).collect();
+        let mut drbg = AesCtrDrbg::new(&entropy_input.try_into().unwrap(), None);
+        let mut seed = [0_u8; 48];
+        drbg.fill_bytes(&mut seed);
+        assert_eq!(
+            hex::encode_upper(seed),
+            "061550234D158C5EC95595FE04EF7A25767F2E24CC2BC479D09D86DC9ABCFDE7056A8C266F9EF97ED08541DBD2E1FFA1"
+        );
+        assert_eq!(drbg.reseed_counter(), 2);
+    }
+}
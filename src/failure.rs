@@ -0,0 +1,213 @@
+//! Decryption failure probability of K-PKE, computed as in the failure scripts
+//! of the Kyber specification.
+//!
+//! Decryption recovers `v - s^T u = e^T r - s^T (e_1 + c_u) + e_2 + c_v + mu`,
+//! where `c_u` and `c_v` are the errors of compressing `u` and `v`. The law of
+//! one coefficient of the noise is obtained by convolving the laws of its terms,
+//! and a coefficient is counted as decrypting wrongly when the absolute value of
+//! the noise is at least floor(q/4), which also covers the asymmetric threshold
+//! of a 1 bit. The failure probability is bounded by n times that tail.
+
+const N: usize = 256;
+const Q: i64 = 3329;
+// Probabilities below this are dropped from the tails after each convolution.
+const CLEAN_THRESHOLD: f64 = 1e-90;
+
+/// A probability law on a range of consecutive integers.
+#[derive(Clone, Debug)]
+struct Law {
+    min: i64,
+    probabilities: Vec<f64>,
+}
+
+impl Law {
+    fn point(x: i64) -> Self {
+        Law {
+            min: x,
+            probabilities: vec![1.0],
+        }
+    }
+
+    fn centered_binomial(eta: u8) -> Self {
+        let n = 2 * eta as u64;
+        let total = 2_f64.powi(n as i32);
+        let probabilities = (0..=n).map(|k| binomial(n, k) / total).collect();
+        Law {
+            min: -(eta as i64),
+            probabilities,
+        }
+    }
+
+    // Law of decompress(compress(x)) - x, centered mod q, for uniform x mod q.
+    fn compression_error(d: u8) -> Self {
+        let t = 1_i64 << d;
+        let errors: Vec<i64> = (0..Q)
+            .map(|x| {
+                let y = ((t * x + Q / 2) / Q) % t;
+                let x_prime = (Q * y + t / 2) >> d;
+                let e = (x_prime - x).rem_euclid(Q);
+                if e > Q / 2 {
+                    e - Q
+                } else {
+                    e
+                }
+            })
+            .collect();
+        let min = *errors.iter().min().unwrap();
+        let max = *errors.iter().max().unwrap();
+        let mut probabilities = vec![0.0; (max - min + 1) as usize];
+        for e in errors {
+            probabilities[(e - min) as usize] += 1.0 / Q as f64;
+        }
+        Law { min, probabilities }
+    }
+
+    fn convolution(&self, other: &Law) -> Law {
+        let mut probabilities = vec![0.0; self.probabilities.len() + other.probabilities.len() - 1];
+        for (i, p) in self.probabilities.iter().enumerate() {
+            for (j, q) in other.probabilities.iter().enumerate() {
+                probabilities[i + j] += p * q;
+            }
+        }
+        Law {
+            min: self.min + other.min,
+            probabilities,
+        }
+        .clean()
+    }
+
+    // Law of the product of independent samples of the two laws.
+    fn product(&self, other: &Law) -> Law {
+        let mut products = vec![];
+        for (i, p) in self.probabilities.iter().enumerate() {
+            for (j, q) in other.probabilities.iter().enumerate() {
+                products.push(((self.min + i as i64) * (other.min + j as i64), p * q));
+            }
+        }
+        let min = products.iter().map(|&(x, _)| x).min().unwrap();
+        let max = products.iter().map(|&(x, _)| x).max().unwrap();
+        let mut probabilities = vec![0.0; (max - min + 1) as usize];
+        for (x, p) in products {
+            probabilities[(x - min) as usize] += p;
+        }
+        Law { min, probabilities }
+    }
+
+    // Law of the sum of `n` independent samples.
+    fn iterated_convolution(&self, n: usize) -> Law {
+        let mut result = Law::point(0);
+        for bit in (0..usize::BITS - n.leading_zeros()).rev() {
+            result = result.convolution(&result);
+            if (n >> bit) & 1 == 1 {
+                result = result.convolution(self);
+            }
+        }
+        result
+    }
+
+    fn clean(mut self) -> Law {
+        let first = self
+            .probabilities
+            .iter()
+            .position(|&p| p >= CLEAN_THRESHOLD)
+            .unwrap_or(0);
+        let last = self
+            .probabilities
+            .iter()
+            .rposition(|&p| p >= CLEAN_THRESHOLD)
+            .unwrap_or(0);
+        self.probabilities = self.probabilities[first..=last].to_vec();
+        self.min += first as i64;
+        self
+    }
+
+    fn variance(&self) -> f64 {
+        let moment = |power: i32| -> f64 {
+            self.probabilities
+                .iter()
+                .enumerate()
+                .map(|(i, p)| p * ((self.min + i as i64) as f64).powi(power))
+                .sum()
+        };
+        moment(2) - moment(1).powi(2)
+    }
+
+    // P(|X| >= bound)
+    fn tail(&self, bound: i64) -> f64 {
+        self.probabilities
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| (self.min + i as i64).abs() >= bound)
+            .map(|(_, p)| p)
+            .sum()
+    }
+}
+
+fn binomial(n: u64, k: u64) -> f64 {
+    (1..=k).fold(1.0, |acc, i| acc * (n + 1 - i) as f64 / i as f64)
+}
+
+// Variance of the noise in one ciphertext coefficient compressed to `d` bits,
+// CBD_eta_2 plus the compression error.
+pub(crate) fn ciphertext_noise_variance(eta_2: u8, d: u8) -> f64 {
+    Law::centered_binomial(eta_2)
+        .convolution(&Law::compression_error(d))
+        .variance()
+}
+
+/// Computes the decryption failure probability for the parameters of
+/// `MLKem::new`.
+pub fn failure_probability(k: u8, eta_1: u8, eta_2: u8, du: u8, dv: u8) -> Result<f64, String> {
+    if !(1..=127).contains(&k) {
+        return Err(String::from("k must be between 1 and 127"));
+    }
+    if !(1..=3).contains(&eta_1) || !(1..=3).contains(&eta_2) {
+        return Err(String::from("eta_1 and eta_2 must be between 1 and 3"));
+    }
+    if !(1..=11).contains(&du) || !(1..=11).contains(&dv) {
+        return Err(String::from("du and dv must be between 1 and 11"));
+    }
+
+    let s = Law::centered_binomial(eta_1);
+    let e_1 = Law::centered_binomial(eta_2).convolution(&Law::compression_error(du));
+    let e_2 = Law::centered_binomial(eta_2).convolution(&Law::compression_error(dv));
+
+    // e^T r and s^T (e_1 + c_u) are sums of k * n products each.
+    let terms = k as usize * N;
+    let e_r = s.product(&s).iterated_convolution(terms);
+    let s_u = s.product(&e_1).iterated_convolution(terms);
+    let noise = e_r.convolution(&s_u).convolution(&e_2);
+
+    Ok((N as f64 * noise.tail(Q / 4)).min(1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::failure_probability;
+    use crate::{ML_KEM_1024, ML_KEM_512, ML_KEM_768};
+
+    #[test]
+    fn standard_parameter_sets() {
+        // 2^-138.8, 2^-164.8 and 2^-174.8 in the specification.
+        for (ml_kem, expected) in [
+            (ML_KEM_512, -138.8),
+            (ML_KEM_768, -164.8),
+            (ML_KEM_1024, -174.8),
+        ] {
+            let log2 = ml_kem.failure_probability().unwrap().log2();
+            assert!((log2 - expected).abs() < 0.05, "{}", log2);
+        }
+    }
+
+    #[test]
+    fn weaker_parameters_fail_more_often() {
+        let standard = failure_probability(3, 2, 2, 10, 4).unwrap();
+        let compressed = failure_probability(3, 2, 2, 9, 3).unwrap();
+        let noisy = failure_probability(3, 3, 3, 10, 4).unwrap();
+        assert!(compressed > standard);
+        assert!(noisy > standard);
+        assert!(failure_probability(2, 2, 2, 12, 4).is_err());
+        assert!(failure_probability(0, 2, 2, 10, 4).is_err());
+        assert!(failure_probability(128, 2, 2, 10, 4).is_err());
+    }
+}
@@ -0,0 +1,330 @@
+use std::{cell::RefCell, panic};
+
+use num_bigint::BigUint;
+use num_traits::Zero;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fault {
+    // Flip one bit of the named intermediate.
+    FlipBit(usize),
+    // Skip the n-th execution of the operation that writes the intermediate.
+    Skip(usize),
+}
+
+struct Injection {
+    point: &'static str,
+    fault: Fault,
+    hits: usize,
+}
+
+thread_local! {
+    static INJECTION: RefCell<Option<Injection>> = const { RefCell::new(None) };
+}
+
+pub fn with_fault<T>(point: &'static str, fault: Fault, f: impl FnOnce() -> T) -> T {
+    struct Reset;
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            INJECTION.with(|injection| *injection.borrow_mut() = None);
+        }
+    }
+
+    INJECTION.with(|injection| {
+        *injection.borrow_mut() = Some(Injection {
+            point,
+            fault,
+            hits: 0,
+        })
+    });
+    let _reset = Reset;
+    f()
+}
+
+fn hit(point: &str) -> Option<(Fault, usize)> {
+    hits(point, 1)
+}
+
+// Records `count` executions at `point`, returning the number before them.
+fn hits(point: &str, count: usize) -> Option<(Fault, usize)> {
+    INJECTION.with(|injection| match injection.borrow_mut().as_mut() {
+        Some(injection) if injection.point == point => {
+            injection.hits += count;
+            Some((injection.fault, injection.hits - count))
+        }
+        _ => None,
+    })
+}
+
+pub fn skip(point: &str) -> bool {
+    matches!(hit(point), Some((Fault::Skip(n), hits)) if n == hits)
+}
+
+pub fn flag(point: &str, value: bool) -> bool {
+    match hit(point) {
+        Some((Fault::FlipBit(_), _)) => !value,
+        Some((Fault::Skip(n), hits)) if n == hits => true,
+        _ => value,
+    }
+}
+
+pub fn bytes(point: &str, mut value: Vec<u8>) -> Vec<u8> {
+    match hit(point) {
+        Some((Fault::FlipBit(bit), _)) if bit < 8 * value.len() => {
+            value[bit / 8] ^= 1 << (bit % 8);
+        }
+        Some((Fault::Skip(n), hits)) if n == hits => value.fill(0),
+        _ => {}
+    }
+    value
+}
+
+// Every coefficient written counts as one execution, so across a polynomial
+// vector `Skip(n)` zeroes the n-th coefficient and `FlipBit(bit)` flips bit
+// `bit % 12` of coefficient `bit / 12`.
+pub fn coefficients(point: &str, coefficients: &mut [BigUint]) {
+    let Some((fault, base)) = hits(point, coefficients.len()) else {
+        return;
+    };
+    let range = base..base + coefficients.len();
+    match fault {
+        Fault::FlipBit(bit) if range.contains(&(bit / 12)) => {
+            coefficients[bit / 12 - base] ^= BigUint::from(1_u32 << (bit % 12));
+        }
+        Fault::Skip(n) if range.contains(&n) => coefficients[n - base] = BigUint::zero(),
+        _ => {}
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Outcome {
+    Unaffected,
+    WrongKey,
+    RejectionKey,
+    DetectedFault,
+    Error(String),
+    Crash,
+}
+
+pub fn simulate(
+    ml_kem: &crate::MLKem,
+    dk: &[u8],
+    c: &[u8],
+    expected: &[u8],
+    point: &'static str,
+    fault: Fault,
+) -> Outcome {
+    let z = &dk[dk.len() - 32..];
+    let rejection = ml_kem._j(&[z, c].concat());
+    let result = panic::catch_unwind(|| with_fault(point, fault, || ml_kem.try_decaps(dk, c)));
+    match result {
+        Err(_) => Outcome::Crash,
+        Ok(Err(error)) if error == crate::FAULT_DETECTED => Outcome::DetectedFault,
+        Ok(Err(error)) => Outcome::Error(error),
+        Ok(Ok(key)) if key == expected => Outcome::Unaffected,
+        Ok(Ok(key)) if key == rejection => Outcome::RejectionKey,
+        Ok(Ok(_)) => Outcome::WrongKey,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "fault-hardened")]
+    use super::with_fault;
+    use super::{simulate, Fault, Outcome};
+    #[cfg(feature = "fault-hardened")]
+    use crate::FAULT_DETECTED;
+    use crate::ML_KEM_512;
+
+    // The expected outcome for a valid and an invalid ciphertext, without and
+    // with `fault-hardened`. For an invalid ciphertext `Unaffected` means the
+    // rejection key was returned.
+    fn injections() -> Vec<(&'static str, Fault, [Outcome; 2], [Outcome; 2])> {
+        use Outcome::*;
+        let hash_error = Error(String::from("hash check failed"));
+        let invalid_rings = Error(String::from("Invalid rings"));
+        vec![
+            (
+                "hash_check",
+                Fault::FlipBit(0),
+                [hash_error.clone(), hash_error],
+                [DetectedFault, DetectedFault],
+            ),
+            (
+                "hash_check",
+                Fault::Skip(0),
+                [Unaffected, Unaffected],
+                [Unaffected, Unaffected],
+            ),
+            (
+                "m_prime",
+                Fault::FlipBit(0),
+                [RejectionKey, Unaffected],
+                [RejectionKey, Unaffected],
+            ),
+            (
+                "m_prime",
+                Fault::Skip(0),
+                [RejectionKey, Unaffected],
+                [RejectionKey, Unaffected],
+            ),
+            (
+                "r_prime",
+                Fault::FlipBit(0),
+                [RejectionKey, Unaffected],
+                [RejectionKey, Unaffected],
+            ),
+            (
+                "r_prime",
+                Fault::Skip(0),
+                [RejectionKey, Unaffected],
+                [RejectionKey, Unaffected],
+            ),
+            // Changing a coefficient of u by 1 moves w by far less than q/4,
+            // so m is unchanged.
+            (
+                "u",
+                Fault::FlipBit(0),
+                [Unaffected, Unaffected],
+                [Unaffected, Unaffected],
+            ),
+            (
+                "u",
+                Fault::FlipBit(11),
+                [RejectionKey, Unaffected],
+                [RejectionKey, Unaffected],
+            ),
+            (
+                "u",
+                Fault::Skip(0),
+                [RejectionKey, Unaffected],
+                [RejectionKey, Unaffected],
+            ),
+            (
+                "ntt_butterfly",
+                Fault::Skip(0),
+                [RejectionKey, Unaffected],
+                [DetectedFault, DetectedFault],
+            ),
+            (
+                "ntt_butterfly",
+                Fault::Skip(1000),
+                [RejectionKey, Unaffected],
+                [DetectedFault, DetectedFault],
+            ),
+            // An unsampled matrix entry has no coefficients, so multiplying by it
+            // fails.
+            (
+                "matrix_sample",
+                Fault::Skip(0),
+                [invalid_rings.clone(), invalid_rings],
+                [DetectedFault, DetectedFault],
+            ),
+            (
+                "mat_mul_term",
+                Fault::Skip(0),
+                [RejectionKey, Unaffected],
+                [RejectionKey, Unaffected],
+            ),
+            (
+                "comparison",
+                Fault::FlipBit(0),
+                [RejectionKey, WrongKey],
+                [DetectedFault, DetectedFault],
+            ),
+            (
+                "comparison",
+                Fault::Skip(0),
+                [Unaffected, WrongKey],
+                [Unaffected, DetectedFault],
+            ),
+            (
+                "select",
+                Fault::FlipBit(0),
+                [WrongKey, WrongKey],
+                [DetectedFault, DetectedFault],
+            ),
+            (
+                "select",
+                Fault::FlipBit(255),
+                [WrongKey, WrongKey],
+                [DetectedFault, DetectedFault],
+            ),
+            (
+                "select",
+                Fault::Skip(0),
+                [WrongKey, WrongKey],
+                [DetectedFault, DetectedFault],
+            ),
+        ]
+    }
+
+    // One row per injection with the outcomes for the valid and the invalid
+    // ciphertext, shown by `cargo test decaps_fault_coverage -- --nocapture`.
+    fn coverage_table(outcomes: &[(&str, Fault, [Outcome; 2], [Outcome; 2])]) -> String {
+        let mut table = format!(
+            "{:<15} {:<13} {:<32} {}\n",
+            "point", "fault", "valid", "invalid"
+        );
+        for (point, fault, [valid, invalid], _) in outcomes {
+            let cells = [fault as &dyn std::fmt::Debug, valid, invalid].map(|x| format!("{:?}", x));
+            table += &format!(
+                "{:<15} {:<13} {:<32} {}\n",
+                point, cells[0], cells[1], cells[2]
+            );
+        }
+        table
+    }
+
+    #[test]
+    #[cfg(feature = "fault-hardened")]
+    fn hardened_faults_are_detected() {
+        let ml_kem = ML_KEM_512;
+        let result = with_fault("t_hat", Fault::FlipBit(0), || ml_kem.try_keygen());
+        assert_eq!(result.unwrap_err(), FAULT_DETECTED);
+
+        let (ek, dk) = ml_kem.keygen();
+        let (_, c) = ml_kem.encaps(&ek);
+        let result = with_fault("comparison", Fault::FlipBit(0), || {
+            ml_kem.try_decaps(&dk, &c)
+        });
+        assert_eq!(result.unwrap_err(), FAULT_DETECTED);
+    }
+
+    #[test]
+    fn decaps_fault_coverage() {
+        let ml_kem = ML_KEM_512;
+        let (ek, dk) = ml_kem._keygen_internal(&[1; 32], &[2; 32]).unwrap();
+        let (k, c) = ml_kem._encaps_internal(&ek, &[3; 32]).unwrap();
+        let mut c_invalid = c.clone();
+        c_invalid[0] ^= 1;
+        let z = &dk[dk.len() - 32..];
+        let rejection = ml_kem._j(&[z, &c_invalid].concat());
+
+        // The panic hook is process-global, so it is left alone and the
+        // panics of `Crash` outcomes are printed.
+        let mut outcomes = vec![];
+        for (point, fault, unhardened, hardened) in injections() {
+            let valid = simulate(&ml_kem, &dk, &c, &k, point, fault);
+            let invalid = simulate(&ml_kem, &dk, &c_invalid, &rejection, point, fault);
+            let expected = if cfg!(feature = "fault-hardened") {
+                hardened
+            } else {
+                unhardened
+            };
+            outcomes.push((point, fault, [valid, invalid], expected));
+        }
+        println!("{}", coverage_table(&outcomes));
+
+        for (point, fault, actual, expected) in outcomes {
+            // A shuffled NTT may skip a butterfly whose inputs are both zero.
+            if cfg!(feature = "shuffling")
+                && point == "ntt_butterfly"
+                && actual[0] == Outcome::Unaffected
+            {
+                assert_eq!(actual[1], expected[1], "{} {:?}", point, fault);
+                continue;
+            }
+            assert_eq!(actual, expected, "{} {:?}", point, fault);
+        }
+    }
+}
@@ -0,0 +1,274 @@
+//! Low-level access to ML-KEM internals for research. Nothing here is needed to
+//! use the KEM, and the values returned are secret.
+
+pub mod hash;
+
+use num_traits::ToPrimitive;
+
+use crate::{self_test, HashBackend, MLKem, Sha3Backend};
+
+/// The noise of each coefficient of `w = v - s^T u` relative to the decrypted
+/// message, before rounding.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NoiseProfile {
+    message: Vec<u8>,
+    noise: Vec<i16>,
+    q: i16,
+}
+
+impl NoiseProfile {
+    /// The decrypted message `m'`.
+    pub fn message(&self) -> &[u8] {
+        &self.message
+    }
+
+    /// `w - Decompress_1(m')` per coefficient, centered in (-q/2, q/2].
+    pub fn noise(&self) -> &[i16] {
+        &self.noise
+    }
+
+    /// The distance of each coefficient to the nearest value that would round
+    /// to the other bit. A margin of 0 means one more unit of noise flips it.
+    pub fn margins(&self) -> Vec<i16> {
+        self.noise
+            .iter()
+            .enumerate()
+            .map(|(i, &noise)| {
+                let bit = (self.message[i / 8] >> (i % 8)) & 1;
+                // For ML-KEM, Decompress_1(1) = 1665 rounds back to 1 for noise
+                // in [-832, 831], and 0 for noise in [-832, 832].
+                let bound = self.q / 4;
+                let upper = if bit == 1 { (self.q - 3) / 4 } else { bound };
+                if noise >= 0 {
+                    upper - noise
+                } else {
+                    bound + noise
+                }
+            })
+            .collect()
+    }
+
+    pub fn min_margin(&self) -> i16 {
+        self.margins().into_iter().min().unwrap()
+    }
+
+    pub fn standard_deviation(&self) -> f64 {
+        let n = self.noise.len() as f64;
+        let mean = self.noise.iter().map(|&x| x as f64).sum::<f64>() / n;
+        let variance = self
+            .noise
+            .iter()
+            .map(|&x| (x as f64 - mean).powi(2))
+            .sum::<f64>()
+            / n;
+        variance.sqrt()
+    }
+}
+
+/// Decrypts `c` with `dk` and returns the noise profile of the decryption.
+pub fn noise_profile<H: HashBackend>(
+    ml_kem: &MLKem<H>,
+    dk: &[u8],
+    c: &[u8],
+) -> Result<NoiseProfile, String> {
+    self_test::check_state()?;
+    ml_kem._check_ciphertext(c)?;
+    ml_kem._check_decapsulation_key(dk)?;
+    let dk_pke = &dk[..ml_kem._dk_pke_size()];
+    let q = ml_kem.ring.q() as i32;
+
+    let w = ml_kem._k_pke_decrypt_w(dk_pke, c)?;
+    let m = w.compress(1);
+    let noise = w
+        .coefficients()
+        .iter()
+        .zip(m.decompress(1).coefficients())
+        .map(|(x, y)| {
+            let e = (x.to_i32().unwrap() - y.to_i32().unwrap()).rem_euclid(q);
+            (if e > q / 2 { e - q } else { e }) as i16
+        })
+        .collect();
+
+    Ok(NoiseProfile {
+        message: m.encode(1),
+        noise,
+        q: q as i16,
+    })
+}
+
+/// K-PKE, the public-key encryption scheme underlying ML-KEM (FIPS 203,
+/// section 5), with the encryption randomness chosen by the caller.
+///
+/// **K-PKE is only IND-CPA secure.** Ciphertexts are malleable, answering
+/// decryption queries on chosen ciphertexts leaks the decryption key, and
+/// encrypting two messages with the same `r` under the same key reveals their
+/// difference. It is meant as a building block for protocols that supply
+/// these guarantees themselves; use [`MLKem`] for key establishment.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KPke<H = Sha3Backend> {
+    ml_kem: MLKem<H>,
+}
+
+/// A K-PKE encryption key `ek_pke`, the same bytes as an ML-KEM encapsulation
+/// key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EncryptionKey {
+    bytes: Vec<u8>,
+}
+
+/// A K-PKE decryption key `dk_pke`, the NTT of the secret vector `s`. It is
+/// not `PartialEq`, as a derived comparison would not run in constant time.
+#[derive(Clone)]
+pub struct DecryptionKey {
+    bytes: Vec<u8>,
+}
+
+impl EncryptionKey {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl DecryptionKey {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl<H: HashBackend + Clone> KPke<H> {
+    /// K-PKE over the parameter set and hash backend of `ml_kem`.
+    pub fn new(ml_kem: &MLKem<H>) -> Self {
+        KPke {
+            ml_kem: ml_kem.clone(),
+        }
+    }
+
+    /// Derives a key pair deterministically from the seed `d`, as
+    /// `K-PKE.KeyGen` does inside ML-KEM key generation.
+    pub fn keygen(&self, d: &[u8; 32]) -> Result<(EncryptionKey, DecryptionKey), String> {
+        self_test::check_state()?;
+        let (ek, dk) = self.ml_kem._k_pke_keygen(d)?;
+        Ok((EncryptionKey { bytes: ek }, DecryptionKey { bytes: dk }))
+    }
+
+    /// Checks the length and the modulus of an encryption key.
+    pub fn encryption_key(&self, bytes: &[u8]) -> Result<EncryptionKey, String> {
+        self.ml_kem._check_encapsulation_key(bytes)?;
+        Ok(EncryptionKey {
+            bytes: bytes.to_vec(),
+        })
+    }
+
+    /// Checks the length of a decryption key.
+    pub fn decryption_key(&self, bytes: &[u8]) -> Result<DecryptionKey, String> {
+        if bytes.len() != self.ml_kem._dk_pke_size() {
+            return Err(String::from("decryption key type check failed"));
+        }
+        Ok(DecryptionKey {
+            bytes: bytes.to_vec(),
+        })
+    }
+
+    /// Encrypts `m`, of [`MLKem::message_size`] bytes (32 for ML-KEM), with
+    /// the randomness `r`. The same `r` must never be used twice.
+    pub fn encrypt(&self, ek: &EncryptionKey, m: &[u8], r: &[u8; 32]) -> Result<Vec<u8>, String> {
+        self_test::check_state()?;
+        if m.len() != self.ml_kem.message_size() {
+            return Err(String::from("message type check failed"));
+        }
+        self.ml_kem._k_pke_encrypt(&ek.bytes, m, r)
+    }
+
+    /// Decrypts `c`. Any ciphertext of the right length decrypts to some
+    /// message, there is no integrity check.
+    pub fn decrypt(&self, dk: &DecryptionKey, c: &[u8]) -> Result<Vec<u8>, String> {
+        self_test::check_state()?;
+        self.ml_kem._check_ciphertext(c)?;
+        if dk.bytes.len() != self.ml_kem._dk_pke_size() {
+            return Err(String::from("decryption key type check failed"));
+        }
+        self.ml_kem._k_pke_decrypt(&dk.bytes, c)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{noise_profile, KPke};
+    use crate::{ML_KEM_1024, ML_KEM_512, ML_KEM_768};
+
+    #[test]
+    fn honest_and_crafted_ciphertexts() {
+        let ml_kem = ML_KEM_768;
+        let (ek, dk) = ml_kem._keygen_internal(&[1; 32], &[2; 32]).unwrap();
+        let m = [0xa5; 32];
+        let (_, c) = ml_kem._encaps_internal(&ek, &m).unwrap();
+
+        let profile = noise_profile(&ml_kem, &dk, &c).unwrap();
+        assert_eq!(profile.message(), m);
+        assert_eq!(profile.noise().len(), 256);
+        assert!(profile.min_margin() > 0);
+        assert!(profile.margins().iter().all(|&x| (0..=832).contains(&x)));
+        assert!(profile.standard_deviation() > 10.0);
+        assert!(profile.standard_deviation() < 200.0);
+
+        // With u = 0 the noise no longer depends on the key.
+        let crafted = vec![0; c.len()];
+        let profile = noise_profile(&ml_kem, &dk, &crafted).unwrap();
+        assert_eq!(profile.standard_deviation(), 0.0);
+        assert_eq!(profile.min_margin(), 832);
+
+        assert!(noise_profile(&ml_kem, &dk, &c[1..]).is_err());
+        assert!(noise_profile(&ML_KEM_512, &dk, &c).is_err());
+    }
+
+    // K-PKE matches the scheme inside ML-KEM: the keys are the first parts of
+    // the ML-KEM keys and encrypting with r from G(m || H(ek)) gives the
+    // encapsulation ciphertext.
+    #[test]
+    fn k_pke_matches_ml_kem() {
+        for ml_kem in [ML_KEM_512, ML_KEM_768, ML_KEM_1024] {
+            let k_pke = KPke::new(&ml_kem);
+            let (ek, dk) = k_pke.keygen(&[1; 32]).unwrap();
+            let (ek_kem, dk_kem) = ml_kem._keygen_internal(&[1; 32], &[2; 32]).unwrap();
+            assert_eq!(ek.as_bytes(), ek_kem);
+            assert_eq!(dk.as_bytes(), &dk_kem[..dk.as_bytes().len()]);
+
+            let m = [0x3c; 32];
+            let (_, r) = ml_kem._g(&[m.as_slice(), &ml_kem._h(&ek_kem)].concat());
+            let c = k_pke.encrypt(&ek, &m, &r.try_into().unwrap()).unwrap();
+            assert_eq!(c, ml_kem._encaps_internal(&ek_kem, &m).unwrap().1);
+            assert_eq!(k_pke.decrypt(&dk, &c).unwrap(), m);
+
+            assert_eq!(k_pke.encryption_key(ek.as_bytes()).unwrap(), ek);
+            assert_eq!(
+                k_pke.decryption_key(dk.as_bytes()).unwrap().as_bytes(),
+                dk.as_bytes()
+            );
+            assert!(k_pke.encryption_key(&ek.as_bytes()[1..]).is_err());
+            assert!(k_pke
+                .encryption_key(&vec![0xff; ek.as_bytes().len()])
+                .is_err());
+            assert!(k_pke.decryption_key(&dk.as_bytes()[1..]).is_err());
+            assert!(k_pke.encrypt(&ek, &m[1..], &[0; 32]).is_err());
+            assert!(k_pke.decrypt(&dk, &c[1..]).is_err());
+        }
+    }
+
+    // Keys of another parameter set are rejected instead of panicking.
+    #[test]
+    fn keys_of_another_parameter_set() {
+        let k_pke_512 = KPke::new(&ML_KEM_512);
+        let k_pke_768 = KPke::new(&ML_KEM_768);
+        let (ek_512, dk_512) = k_pke_512.keygen(&[1; 32]).unwrap();
+        let (ek_768, dk_768) = k_pke_768.keygen(&[1; 32]).unwrap();
+        let c_512 = k_pke_512.encrypt(&ek_512, &[0; 32], &[0; 32]).unwrap();
+        let c_768 = k_pke_768.encrypt(&ek_768, &[0; 32], &[0; 32]).unwrap();
+
+        assert_eq!(
+            k_pke_768.decrypt(&dk_512, &c_768).unwrap_err(),
+            "decryption key type check failed"
+        );
+        assert!(k_pke_512.decrypt(&dk_768, &c_512).is_err());
+        assert!(k_pke_768.encrypt(&ek_512, &[0; 32], &[0; 32]).is_err());
+    }
+}
@@ -0,0 +1,164 @@
+//! The hash functions of FIPS 203, section 4.1, exactly as ML-KEM uses them,
+//! for protocols that need compatible derivations. Each function uses the
+//! `sha3` crate, and its `*_with_backend` variant any [`HashBackend`], such as
+//! the one passed to `MLKem::with_backend`.
+
+use sha3::{digest::XofReader, Shake128Reader};
+
+use crate::{HashBackend, Sha3Backend};
+
+/// G(c) = SHA3-512(c), split into two 32-byte halves.
+pub fn g(c: &[u8]) -> ([u8; 32], [u8; 32]) {
+    g_with_backend(&Sha3Backend, c)
+}
+
+pub fn g_with_backend<H: HashBackend>(backend: &H, c: &[u8]) -> ([u8; 32], [u8; 32]) {
+    let result = backend.sha3_512(c);
+    (
+        result[..32].try_into().unwrap(),
+        result[32..].try_into().unwrap(),
+    )
+}
+
+/// H(s) = SHA3-256(s).
+pub fn h(s: &[u8]) -> [u8; 32] {
+    h_with_backend(&Sha3Backend, s)
+}
+
+pub fn h_with_backend<H: HashBackend>(backend: &H, s: &[u8]) -> [u8; 32] {
+    backend.sha3_256(s)
+}
+
+/// J(s) = SHAKE256(s, 8 * 32).
+pub fn j(s: &[u8]) -> [u8; 32] {
+    j_with_backend(&Sha3Backend, s)
+}
+
+pub fn j_with_backend<H: HashBackend>(backend: &H, s: &[u8]) -> [u8; 32] {
+    let mut output = [0u8; 32];
+    backend.shake256(s, &mut output);
+    output
+}
+
+/// PRF_2(s, b) = SHAKE256(s || b, 8 * 128), the PRF of eta = 2.
+pub fn prf_2(s: &[u8; 32], b: u8) -> [u8; 128] {
+    prf_2_with_backend(&Sha3Backend, s, b)
+}
+
+pub fn prf_2_with_backend<H: HashBackend>(backend: &H, s: &[u8; 32], b: u8) -> [u8; 128] {
+    _prf(backend, s, b)
+}
+
+/// PRF_3(s, b) = SHAKE256(s || b, 8 * 192), the PRF of eta = 3.
+pub fn prf_3(s: &[u8; 32], b: u8) -> [u8; 192] {
+    prf_3_with_backend(&Sha3Backend, s, b)
+}
+
+pub fn prf_3_with_backend<H: HashBackend>(backend: &H, s: &[u8; 32], b: u8) -> [u8; 192] {
+    _prf(backend, s, b)
+}
+
+fn _prf<H: HashBackend, const N: usize>(backend: &H, s: &[u8; 32], b: u8) -> [u8; N] {
+    let mut output = [0u8; N];
+    backend.shake256(&[s.as_slice(), &[b]].concat(), &mut output);
+    output
+}
+
+/// XOF(rho, i, j) = SHAKE128(rho || i || j), read incrementally as in
+/// SampleNTT. It can be passed to `algebra::Ring::ntt_sample`.
+pub fn xof(rho: &[u8; 32], i: u8, j: u8) -> Xof {
+    xof_with_backend(&Sha3Backend, rho, i, j)
+}
+
+pub fn xof_with_backend<H: HashBackend>(
+    backend: &H,
+    rho: &[u8; 32],
+    i: u8,
+    j: u8,
+) -> Xof<H::Shake128Reader> {
+    Xof {
+        reader: backend.shake128(&[rho.as_slice(), &[i, j]].concat()),
+    }
+}
+
+/// A SHAKE128 output stream.
+#[derive(Clone)]
+pub struct Xof<R = Shake128Reader> {
+    reader: R,
+}
+
+impl<R: XofReader> Xof<R> {
+    /// Fills `buffer` with the next bytes of the output.
+    pub fn squeeze(&mut self, buffer: &mut [u8]) {
+        self.reader.read(buffer);
+    }
+}
+
+impl<R: XofReader> XofReader for Xof<R> {
+    fn read(&mut self, buffer: &mut [u8]) {
+        self.squeeze(buffer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        algebra::{Ring, RingParams},
+        self_test::{HASH_KAT_MESSAGE, SHA3_256_KAT, SHA3_512_KAT, SHAKE256_KAT},
+        tests::Recording,
+    };
+
+    #[test]
+    fn known_answers() {
+        let (a, b) = g(&HASH_KAT_MESSAGE);
+        assert_eq!(hex::encode([a, b].concat()), SHA3_512_KAT);
+        assert_eq!(hex::encode(h(&HASH_KAT_MESSAGE)), SHA3_256_KAT);
+        assert_eq!(hex::encode(j(&HASH_KAT_MESSAGE)), SHAKE256_KAT);
+
+        // PRF is SHAKE256 over s || b, of which J gives the first 32 bytes.
+        let seed: [u8; 32] = core::array::from_fn(|i| i as u8);
+        let prf_output = prf_2(&seed, 1);
+        assert_eq!(prf_output[..32], j(&[seed.as_slice(), &[1]].concat()));
+        assert_eq!(prf_3(&seed, 1)[..128], prf_output);
+        assert_ne!(prf_2(&seed, 2), prf_output);
+
+        // Squeezing in 3-byte steps gives the same stream as one 840-byte read.
+        let mut reader = xof(&seed, 1, 2);
+        let mut output = vec![];
+        for _ in 0..280 {
+            let mut bytes = [0; 3];
+            reader.squeeze(&mut bytes);
+            output.extend_from_slice(&bytes);
+        }
+        let mut expected = [0; 840];
+        xof(&seed, 1, 2).squeeze(&mut expected);
+        assert_eq!(output, expected);
+
+        let f = Ring::ntt_sample(RingParams::ML_KEM, &mut xof(&seed, 0, 0));
+        let g = Ring::ntt_sample(RingParams::ML_KEM, &mut xof(&seed, 0, 0));
+        assert_eq!(f, g);
+        assert!(f.is_ntt());
+    }
+
+    // The variants forward to the given backend and agree with the defaults.
+    #[test]
+    fn backend_variants() {
+        let backend = Recording::default();
+        let seed = [7; 32];
+        assert_eq!(g_with_backend(&backend, &seed), g(&seed));
+        assert_eq!(h_with_backend(&backend, &seed), h(&seed));
+        assert_eq!(j_with_backend(&backend, &seed), j(&seed));
+        assert_eq!(prf_2_with_backend(&backend, &seed, 1), prf_2(&seed, 1));
+        assert_eq!(prf_3_with_backend(&backend, &seed, 1), prf_3(&seed, 1));
+        let (mut a, mut b) = ([0; 840], [0; 840]);
+        xof_with_backend(&backend, &seed, 0, 1).squeeze(&mut a);
+        xof(&seed, 0, 1).squeeze(&mut b);
+        assert_eq!(a, b);
+
+        assert_eq!(backend.count("SHA3-512"), 1);
+        assert_eq!(backend.count("SHA3-256"), 1);
+        assert_eq!(backend.count("SHAKE256"), 3);
+        assert_eq!(backend.count("SHAKE128"), 1);
+    }
+}
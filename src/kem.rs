@@ -0,0 +1,272 @@
+//! The RustCrypto [`kem`](::kem) traits for ML-KEM-512, ML-KEM-768 and
+//! ML-KEM-1024. Keys, ciphertexts and shared keys are fixed-size
+//! `hybrid_array::Array`s.
+//!
+//! `EncodedSizeUser` and `KemCore` are defined here and only mirror the names
+//! of the `ml-kem` crate. Code generic over `Encapsulate` and `Decapsulate`
+//! works with either crate, but code that names the `ml-kem` traits or types
+//! has to import them from this module instead.
+
+#[cfg(feature = "serde")]
+pub mod serde;
+
+use ::kem::{Decapsulate, Encapsulate};
+use hybrid_array::{
+    sizes::{U1088, U1184, U1568, U1632, U2400, U3168, U768, U800},
+    typenum::U32,
+    Array, ArraySize,
+};
+use rand_core::CryptoRngCore;
+
+use crate::{MLKem, ML_KEM_1024, ML_KEM_512, ML_KEM_768};
+
+/// A 32-byte shared key.
+pub type SharedKey = Array<u8, U32>;
+
+/// An ML-KEM ciphertext for the parameter set `K`.
+pub type Ciphertext<K> = Array<u8, <K as KemCore>::CiphertextSize>;
+
+/// The byte encoding of a key.
+pub type Encoded<T> = Array<u8, <T as EncodedSizeUser>::EncodedSize>;
+
+/// A value with a fixed-size byte encoding.
+pub trait EncodedSizeUser {
+    type EncodedSize: ArraySize;
+
+    fn from_bytes(enc: &Encoded<Self>) -> Self;
+
+    fn as_bytes(&self) -> Encoded<Self>;
+}
+
+/// An ML-KEM parameter set, with its sizes as type-level integers.
+pub trait KemCore: Copy + Clone + std::fmt::Debug + Default + Eq + Send + Sync + 'static {
+    type EncapsulationKeySize: ArraySize;
+    type DecapsulationKeySize: ArraySize;
+    type CiphertextSize: ArraySize;
+
+    const ML_KEM: MLKem;
+
+    /// Generates a key pair, panicking where [`KemCore::try_generate`] fails.
+    fn generate(rng: &mut impl CryptoRngCore) -> (DecapsulationKey<Self>, EncapsulationKey<Self>) {
+        Self::try_generate(rng).unwrap()
+    }
+
+    /// Generates a key pair with [`MLKem::try_keygen_with_rng`].
+    fn try_generate(
+        rng: &mut impl CryptoRngCore,
+    ) -> Result<(DecapsulationKey<Self>, EncapsulationKey<Self>), String> {
+        let (_, dk) = Self::ML_KEM.try_keygen_with_rng(rng)?;
+        let dk = DecapsulationKey::from_bytes(&to_array(&dk));
+        let ek = dk.encapsulation_key().clone();
+        Ok((dk, ek))
+    }
+}
+
+/// ML-KEM-512, NIST security category 1.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct MlKem512;
+
+/// ML-KEM-768, NIST security category 3.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct MlKem768;
+
+/// ML-KEM-1024, NIST security category 5.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct MlKem1024;
+
+impl KemCore for MlKem512 {
+    type EncapsulationKeySize = U800;
+    type DecapsulationKeySize = U1632;
+    type CiphertextSize = U768;
+
+    const ML_KEM: MLKem = ML_KEM_512;
+}
+
+impl KemCore for MlKem768 {
+    type EncapsulationKeySize = U1184;
+    type DecapsulationKeySize = U2400;
+    type CiphertextSize = U1088;
+
+    const ML_KEM: MLKem = ML_KEM_768;
+}
+
+impl KemCore for MlKem1024 {
+    type EncapsulationKeySize = U1568;
+    type DecapsulationKeySize = U3168;
+    type CiphertextSize = U1568;
+
+    const ML_KEM: MLKem = ML_KEM_1024;
+}
+
+/// An encapsulation key. The modulus check of FIPS 203 is done on every
+/// [`Encapsulate::encapsulate`], which returns an error for invalid keys.
+pub struct EncapsulationKey<K: KemCore> {
+    bytes: Array<u8, K::EncapsulationKeySize>,
+}
+
+/// A decapsulation key, with the encapsulation key it contains.
+pub struct DecapsulationKey<K: KemCore> {
+    bytes: Array<u8, K::DecapsulationKeySize>,
+    ek: EncapsulationKey<K>,
+}
+
+// Derived impls would require the size types themselves to be `Clone`,
+// `PartialEq` and `Debug`.
+impl<K: KemCore> Clone for EncapsulationKey<K> {
+    fn clone(&self) -> Self {
+        EncapsulationKey {
+            bytes: self.bytes.clone(),
+        }
+    }
+}
+
+impl<K: KemCore> PartialEq for EncapsulationKey<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.bytes == other.bytes
+    }
+}
+
+impl<K: KemCore> Eq for EncapsulationKey<K> {}
+
+impl<K: KemCore> std::fmt::Debug for EncapsulationKey<K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EncapsulationKey")
+            .field("bytes", &self.bytes)
+            .finish()
+    }
+}
+
+impl<K: KemCore> Clone for DecapsulationKey<K> {
+    fn clone(&self) -> Self {
+        DecapsulationKey {
+            bytes: self.bytes.clone(),
+            ek: self.ek.clone(),
+        }
+    }
+}
+
+impl<K: KemCore> PartialEq for DecapsulationKey<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.bytes == other.bytes
+    }
+}
+
+impl<K: KemCore> Eq for DecapsulationKey<K> {}
+
+impl<K: KemCore> DecapsulationKey<K> {
+    pub fn encapsulation_key(&self) -> &EncapsulationKey<K> {
+        &self.ek
+    }
+}
+
+impl<K: KemCore> EncodedSizeUser for EncapsulationKey<K> {
+    type EncodedSize = K::EncapsulationKeySize;
+
+    fn from_bytes(enc: &Encoded<Self>) -> Self {
+        EncapsulationKey { bytes: enc.clone() }
+    }
+
+    fn as_bytes(&self) -> Encoded<Self> {
+        self.bytes.clone()
+    }
+}
+
+impl<K: KemCore> EncodedSizeUser for DecapsulationKey<K> {
+    type EncodedSize = K::DecapsulationKeySize;
+
+    fn from_bytes(enc: &Encoded<Self>) -> Self {
+        // dk = dk_pke || ek || H(ek) || z
+        let n = K::ML_KEM._dk_pke_size();
+        let ek = EncapsulationKey::from_bytes(&to_array(&enc[n..(2 * n + 32)]));
+        DecapsulationKey {
+            bytes: enc.clone(),
+            ek,
+        }
+    }
+
+    fn as_bytes(&self) -> Encoded<Self> {
+        self.bytes.clone()
+    }
+}
+
+impl<K: KemCore> Encapsulate<Ciphertext<K>, SharedKey> for EncapsulationKey<K> {
+    type Error = String;
+
+    fn encapsulate(
+        &self,
+        rng: &mut impl CryptoRngCore,
+    ) -> Result<(Ciphertext<K>, SharedKey), String> {
+        let (k, c) = K::ML_KEM.try_encaps_with_rng(&self.bytes, rng)?;
+        Ok((to_array(&c), to_array(&k)))
+    }
+}
+
+impl<K: KemCore> Decapsulate<Ciphertext<K>, SharedKey> for DecapsulationKey<K> {
+    type Error = String;
+
+    fn decapsulate(&self, encapsulated_key: &Ciphertext<K>) -> Result<SharedKey, String> {
+        let k = K::ML_KEM.try_decaps(&self.bytes, encapsulated_key.as_slice())?;
+        Ok(to_array(&k))
+    }
+}
+
+// The lengths are fixed by the parameter set, so this cannot fail.
+fn to_array<U: ArraySize>(bytes: &[u8]) -> Array<u8, U> {
+    Array::try_from(bytes).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hybrid_array::typenum::Unsigned;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn round_trip<K: KemCore>() {
+        let ml_kem = K::ML_KEM;
+        assert_eq!(
+            K::EncapsulationKeySize::USIZE,
+            ml_kem.encapsulation_key_size()
+        );
+        assert_eq!(
+            K::DecapsulationKeySize::USIZE,
+            ml_kem.decapsulation_key_size()
+        );
+        assert_eq!(K::CiphertextSize::USIZE, ml_kem.ciphertext_size());
+
+        let (dk, ek) = K::generate(&mut StdRng::seed_from_u64(1));
+        let (ek_bytes, dk_bytes) = ml_kem
+            .try_keygen_with_rng(&mut StdRng::seed_from_u64(1))
+            .unwrap();
+        assert_eq!(ek.as_bytes().as_slice(), ek_bytes);
+        assert_eq!(dk.as_bytes().as_slice(), dk_bytes);
+        assert_eq!(dk.encapsulation_key(), &ek);
+        assert!(DecapsulationKey::<K>::from_bytes(&dk.as_bytes()) == dk);
+        assert_eq!(EncapsulationKey::<K>::from_bytes(&ek.as_bytes()), ek);
+
+        let (c, k) = ek.encapsulate(&mut StdRng::seed_from_u64(2)).unwrap();
+        let (k_bytes, c_bytes) = ml_kem
+            .try_encaps_with_rng(&ek_bytes, &mut StdRng::seed_from_u64(2))
+            .unwrap();
+        assert_eq!(c.as_slice(), c_bytes);
+        assert_eq!(k.as_slice(), k_bytes);
+        assert_eq!(dk.decapsulate(&c).unwrap(), k);
+
+        // A modified ciphertext decapsulates to the implicit rejection key.
+        let mut modified = c.clone();
+        modified[0] ^= 1;
+        let rejected = dk.decapsulate(&modified).unwrap();
+        assert_ne!(rejected, k);
+        assert_eq!(rejected.as_slice(), ml_kem.decaps(&dk_bytes, &modified));
+
+        // Coefficients of 0xfff are not reduced mod q.
+        let invalid = EncapsulationKey::<K>::from_bytes(&Array::from_fn(|_| 0xff));
+        assert!(invalid.encapsulate(&mut StdRng::seed_from_u64(3)).is_err());
+    }
+
+    #[test]
+    fn kem_traits() {
+        round_trip::<MlKem512>();
+        round_trip::<MlKem768>();
+        round_trip::<MlKem1024>();
+    }
+}
@@ -0,0 +1,267 @@
+//! Serialize and Deserialize for the key types, and [`ciphertext`] and
+//! [`shared_key`] modules for `#[serde(with = "...")]` on the `Array`s that
+//! hold ciphertexts and shared keys. Values are lowercase hex in
+//! human-readable formats such as JSON and raw bytes in binary formats such as
+//! CBOR. Deserialization checks the length for the parameter set, the modulus
+//! of encapsulation keys and the `H(ek)` of decapsulation keys.
+
+use std::fmt;
+
+use ::serde::{
+    de::{Error, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use super::{to_array, DecapsulationKey, EncapsulationKey, EncodedSizeUser, KemCore};
+
+fn serialize_bytes<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        serializer.serialize_str(&hex::encode(bytes))
+    } else {
+        serializer.serialize_bytes(bytes)
+    }
+}
+
+fn deserialize_bytes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    if deserializer.is_human_readable() {
+        let hex = String::deserialize(deserializer)?;
+        hex::decode(hex).map_err(D::Error::custom)
+    } else {
+        deserializer.deserialize_byte_buf(BytesVisitor)
+    }
+}
+
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a byte string")
+    }
+
+    fn visit_bytes<E: Error>(self, v: &[u8]) -> Result<Vec<u8>, E> {
+        Ok(v.to_vec())
+    }
+
+    fn visit_byte_buf<E: Error>(self, v: Vec<u8>) -> Result<Vec<u8>, E> {
+        Ok(v)
+    }
+
+    // Formats without a byte string type encode bytes as a sequence.
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u8>, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+        Ok(bytes)
+    }
+}
+
+fn check_length(bytes: &[u8], expected: usize, name: &str) -> Result<(), String> {
+    if bytes.len() != expected {
+        return Err(format!(
+            "{} has {} bytes, expected {}",
+            name,
+            bytes.len(),
+            expected
+        ));
+    }
+    Ok(())
+}
+
+impl<K: KemCore> Serialize for EncapsulationKey<K> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_bytes(&self.bytes, serializer)
+    }
+}
+
+impl<'de, K: KemCore> Deserialize<'de> for EncapsulationKey<K> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = deserialize_bytes(deserializer)?;
+        let ml_kem = K::ML_KEM;
+        check_length(&bytes, ml_kem.encapsulation_key_size(), "encapsulation key")
+            .map_err(D::Error::custom)?;
+        ml_kem
+            ._check_encapsulation_key(&bytes)
+            .map_err(D::Error::custom)?;
+        Ok(EncapsulationKey::from_bytes(&to_array(&bytes)))
+    }
+}
+
+impl<K: KemCore> Serialize for DecapsulationKey<K> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_bytes(&self.bytes, serializer)
+    }
+}
+
+impl<'de, K: KemCore> Deserialize<'de> for DecapsulationKey<K> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = deserialize_bytes(deserializer)?;
+        let ml_kem = K::ML_KEM;
+        check_length(&bytes, ml_kem.decapsulation_key_size(), "decapsulation key")
+            .map_err(D::Error::custom)?;
+        let n = ml_kem._dk_pke_size();
+        if ml_kem._h(&bytes[n..(2 * n + 32)]) != bytes[(2 * n + 32)..(2 * n + 64)] {
+            return Err(D::Error::custom("hash check failed"));
+        }
+        Ok(DecapsulationKey::from_bytes(&to_array(&bytes)))
+    }
+}
+
+/// `#[serde(with = "pqc_ml_kem::kem::serde::ciphertext")]` for a
+/// [`Ciphertext<K>`](super::Ciphertext). The length is checked against the
+/// size of the `Array`, which is the ciphertext size of `K`.
+pub mod ciphertext {
+    use ::serde::{de::Error, Deserializer, Serializer};
+    use hybrid_array::{Array, ArraySize};
+
+    use super::{check_length, deserialize_bytes, serialize_bytes};
+    use crate::kem::to_array;
+
+    pub fn serialize<U: ArraySize, S: Serializer>(
+        c: &Array<u8, U>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serialize_bytes(c, serializer)
+    }
+
+    pub fn deserialize<'de, U: ArraySize, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Array<u8, U>, D::Error> {
+        let bytes = deserialize_bytes(deserializer)?;
+        check_length(&bytes, U::USIZE, "ciphertext").map_err(D::Error::custom)?;
+        Ok(to_array(&bytes))
+    }
+}
+
+/// `#[serde(with = "pqc_ml_kem::kem::serde::shared_key")]` for a
+/// [`SharedKey`](super::SharedKey).
+pub mod shared_key {
+    use ::serde::{de::Error, Deserializer, Serializer};
+
+    use super::{check_length, deserialize_bytes, serialize_bytes};
+    use crate::kem::{to_array, SharedKey};
+
+    pub fn serialize<S: Serializer>(k: &SharedKey, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_bytes(k, serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SharedKey, D::Error> {
+        let bytes = deserialize_bytes(deserializer)?;
+        check_length(&bytes, 32, "shared key").map_err(D::Error::custom)?;
+        Ok(to_array(&bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ciphertext, shared_key};
+    use crate::kem::{
+        Ciphertext, DecapsulationKey, EncapsulationKey, EncodedSizeUser, KemCore, MlKem1024,
+        MlKem512, MlKem768, SharedKey,
+    };
+    use ::kem::Encapsulate;
+    use rand::{rngs::StdRng, SeedableRng};
+    use serde_derive::{Deserialize, Serialize};
+
+    // A message carrying both, as a user of the `with` modules would write it.
+    #[derive(Serialize, Deserialize)]
+    struct Exchange<K: KemCore> {
+        #[serde(with = "ciphertext")]
+        c: Ciphertext<K>,
+        #[serde(with = "shared_key")]
+        k: SharedKey,
+    }
+
+    fn round_trip<K: KemCore>() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let (dk, ek) = K::generate(&mut rng);
+        let (c, k) = ek.encapsulate(&mut rng).unwrap();
+
+        let json = serde_json::to_string(&ek).unwrap();
+        assert_eq!(json, format!("\"{}\"", hex::encode(ek.as_bytes())));
+        let ek_json: EncapsulationKey<K> = serde_json::from_str(&json).unwrap();
+        assert_eq!(ek_json, ek);
+        let json = serde_json::to_string(&dk).unwrap();
+        assert!(serde_json::from_str::<DecapsulationKey<K>>(&json).unwrap() == dk);
+        let exchange = Exchange::<K> { c, k };
+        let json = serde_json::to_string(&exchange).unwrap();
+        assert_eq!(
+            json,
+            format!(
+                "{{\"c\":\"{}\",\"k\":\"{}\"}}",
+                hex::encode(exchange.c.as_slice()),
+                hex::encode(exchange.k.as_slice())
+            )
+        );
+        let exchange_json: Exchange<K> = serde_json::from_str(&json).unwrap();
+        assert_eq!(exchange_json.c, exchange.c);
+        assert_eq!(exchange_json.k, exchange.k);
+
+        let mut cbor = Vec::new();
+        ciborium::into_writer(&ek, &mut cbor).unwrap();
+        // A byte string header followed by the key itself.
+        assert!(cbor.ends_with(&ek.as_bytes()));
+        assert_eq!(cbor.len(), ek.as_bytes().len() + 3);
+        let ek_cbor: EncapsulationKey<K> = ciborium::from_reader(cbor.as_slice()).unwrap();
+        assert_eq!(ek_cbor, ek);
+        let mut cbor = Vec::new();
+        ciborium::into_writer(&dk, &mut cbor).unwrap();
+        assert!(ciborium::from_reader::<DecapsulationKey<K>, _>(cbor.as_slice()).unwrap() == dk);
+        let mut cbor = Vec::new();
+        ciborium::into_writer(&exchange, &mut cbor).unwrap();
+        let exchange_cbor: Exchange<K> = ciborium::from_reader(cbor.as_slice()).unwrap();
+        assert_eq!(exchange_cbor.c, exchange.c);
+        assert_eq!(exchange_cbor.k, exchange.k);
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        round_trip::<MlKem512>();
+        round_trip::<MlKem768>();
+        round_trip::<MlKem1024>();
+    }
+
+    #[test]
+    fn serde_validation() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let (dk, ek) = MlKem768::generate(&mut rng);
+        let (c, k) = ek.encapsulate(&mut rng).unwrap();
+
+        // Keys and ciphertexts of another parameter set have the wrong length.
+        let json = serde_json::to_string(&ek).unwrap();
+        assert!(serde_json::from_str::<EncapsulationKey<MlKem512>>(&json).is_err());
+        let json = serde_json::to_string(&dk).unwrap();
+        assert!(serde_json::from_str::<DecapsulationKey<MlKem1024>>(&json).is_err());
+        let json = serde_json::to_string(&Exchange::<MlKem768> { c, k }).unwrap();
+        let error = serde_json::from_str::<Exchange<MlKem512>>(&json)
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("ciphertext has 1088 bytes"));
+        let c = hex::encode([0; 768]);
+        for k in ["00".to_string(), "zz".repeat(32)] {
+            let json = format!("{{\"c\":\"{}\",\"k\":\"{}\"}}", c, k);
+            assert!(serde_json::from_str::<Exchange<MlKem512>>(&json).is_err());
+        }
+        let json = format!("{{\"c\":\"{}\",\"k\":\"{}\"}}", c, "00".repeat(32));
+        assert!(serde_json::from_str::<Exchange<MlKem512>>(&json).is_ok());
+
+        let error = serde_json::from_str::<EncapsulationKey<MlKem768>>(&format!(
+            "\"{}\"",
+            "ff".repeat(1184)
+        ))
+        .unwrap_err();
+        assert!(error.to_string().contains("Modulus check failed"));
+
+        let mut dk_bytes = dk.as_bytes().to_vec();
+        dk_bytes[2 * 1152 + 32] ^= 1;
+        let error = serde_json::from_str::<DecapsulationKey<MlKem768>>(&format!(
+            "\"{}\"",
+            hex::encode(dk_bytes)
+        ))
+        .err()
+        .unwrap();
+        assert!(error.to_string().contains("hash check failed"));
+    }
+}
@@ -0,0 +1,154 @@
+//! Simulated Hamming-weight leakage for side-channel research.
+//!
+//! While a closure runs under [`capture`], every intermediate value written by
+//! the NTT, the NTT base multiplication, CBD sampling and compression is
+//! recorded as its Hamming weight, one sample per write.
+
+use std::cell::RefCell;
+
+use num_bigint::BigUint;
+
+thread_local! {
+    static RECORDING: RefCell<Option<Vec<u8>>> = const { RefCell::new(None) };
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Trace {
+    samples: Vec<u8>,
+}
+
+impl Trace {
+    pub fn samples(&self) -> &[u8] {
+        &self.samples
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+}
+
+/// Runs `f` and returns its result together with the leakage trace it produced.
+pub fn capture<T>(f: impl FnOnce() -> T) -> (T, Trace) {
+    // Restores the enclosing capture, if any, even when `f` panics.
+    struct Restore(Option<Vec<u8>>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            RECORDING.with(|recording| recording.replace(previous));
+        }
+    }
+
+    let previous = RECORDING.with(|recording| recording.replace(Some(vec![])));
+    let restore = Restore(previous);
+    let result = f();
+    let samples = RECORDING.with(|recording| recording.take());
+    drop(restore);
+    let trace = Trace {
+        samples: samples.unwrap_or_default(),
+    };
+    (result, trace)
+}
+
+pub(crate) fn record(value: &BigUint) {
+    RECORDING.with(|recording| {
+        if let Some(samples) = recording.borrow_mut().as_mut() {
+            samples.push(value.count_ones() as u8);
+        }
+    });
+}
+
+/// Formats traces as CSV, one trace per line.
+pub fn to_csv(traces: &[Trace]) -> String {
+    let mut output = String::new();
+    for trace in traces {
+        let row: Vec<String> = trace.samples.iter().map(|x| x.to_string()).collect();
+        output.push_str(&row.join(","));
+        output.push('\n');
+    }
+    output
+}
+
+/// Formats traces as a two-dimensional `uint8` NumPy array in NPY format.
+pub fn to_npy(traces: &[Trace]) -> Result<Vec<u8>, String> {
+    let columns = traces.first().map_or(0, |trace| trace.len());
+    if traces.iter().any(|trace| trace.len() != columns) {
+        return Err(String::from("traces must all have the same length"));
+    }
+
+    let mut header = format!(
+        "{{'descr': '|u1', 'fortran_order': False, 'shape': ({}, {}), }}",
+        traces.len(),
+        columns
+    );
+    // The magic string, version and header length take 10 bytes, and the
+    // header is padded so that the data starts on a 64-byte boundary.
+    let padding = 63 - (10 + header.len()) % 64;
+    header.push_str(&" ".repeat(padding));
+    header.push('\n');
+
+    let mut output = b"\x93NUMPY\x01\x00".to_vec();
+    output.extend((header.len() as u16).to_le_bytes());
+    output.extend(header.into_bytes());
+    for trace in traces {
+        output.extend(&trace.samples);
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic;
+
+    use num_bigint::BigUint;
+
+    use super::{capture, record, to_csv, to_npy, RECORDING};
+    use crate::ML_KEM_512;
+
+    #[test]
+    fn decaps_traces() {
+        let (ek, dk) = ML_KEM_512.keygen();
+        let (k, c) = ML_KEM_512.encaps(&ek);
+
+        let (k_1, trace_1) = capture(|| ML_KEM_512.decaps(&dk, &c));
+        let (k_2, trace_2) = capture(|| ML_KEM_512.decaps(&dk, &c));
+        assert_eq!(k_1, k);
+        assert_eq!(k_2, k);
+        assert!(!trace_1.is_empty());
+        assert_eq!(trace_1.len(), trace_2.len());
+        assert!(trace_1.samples().iter().all(|&x| x <= 32));
+
+        let (_, outside) = capture(|| ());
+        assert!(outside.is_empty());
+
+        let traces = [trace_1, trace_2];
+        let npy = to_npy(&traces).unwrap();
+        assert_eq!(&npy[..6], b"\x93NUMPY");
+        let header_len = u16::from_le_bytes([npy[8], npy[9]]) as usize;
+        assert_eq!((10 + header_len) % 64, 0);
+        assert_eq!(npy.len(), 10 + header_len + 2 * traces[0].len());
+
+        let csv = to_csv(&traces);
+        assert_eq!(csv.lines().count(), 2);
+    }
+
+    #[test]
+    fn capture_restores_state_on_panic() {
+        let value = BigUint::from(7_u8);
+        let (_, outer) = capture(|| {
+            record(&value);
+            let result = panic::catch_unwind(|| capture(|| panic!("inner")));
+            assert!(result.is_err());
+            record(&value);
+        });
+        assert_eq!(outer.samples(), [3, 3]);
+
+        let result = panic::catch_unwind(|| capture(|| panic!("outer")));
+        assert!(result.is_err());
+        record(&value);
+        RECORDING.with(|recording| assert!(recording.borrow().is_none()));
+    }
+}
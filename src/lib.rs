@@ -1,17 +1,42 @@
+pub mod acvp;
+pub mod algebra;
+pub mod backend;
+pub mod drbg;
+pub mod failure;
+#[cfg(test)]
+mod fault;
+pub mod hazmat;
+#[cfg(feature = "kem")]
+pub mod kem;
+#[cfg(feature = "leakage")]
+pub mod leakage;
 mod module;
+#[cfg(test)]
+mod negative;
+#[cfg(any(test, feature = "reference"))]
+pub mod reference;
 mod ring;
+pub mod rsp;
+pub mod security;
+mod self_test;
+#[cfg(test)]
+mod statistics;
+#[cfg(feature = "trace")]
+pub mod trace;
 
 use std::vec;
 
 use module::Module;
-use rand::rngs::OsRng;
-use rand::RngCore;
-use sha3::{
-    digest::{ExtendableOutput, Update, XofReader},
-    Digest, Sha3_256, Sha3_512, Shake128, Shake256,
-};
+#[cfg(feature = "shuffling")]
+use num_bigint::BigUint;
+#[cfg(feature = "shuffling")]
+use rand::Rng;
+use rand::{CryptoRng, RngCore};
 
+pub use backend::{HashBackend, Sha3Backend};
 use ring::Ring;
+pub use ring::RingParams;
+pub use self_test::{self_test, self_test_with_backend, SELF_TEST_FAILED};
 
 pub enum Type {
     MlKem512,
@@ -19,12 +44,16 @@ pub enum Type {
     MlKem1024,
 }
 
-pub struct MLKem {
+/// An ML-KEM parameter set, hashing through the backend `H`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MLKem<H = Sha3Backend> {
     k: u8,
     eta_1: u8,
     eta_2: u8,
     du: u8,
     dv: u8,
+    ring: RingParams,
+    hash: H,
 }
 
 impl MLKem {
@@ -36,6 +65,8 @@ impl MLKem {
                 eta_2: 2,
                 du: 10,
                 dv: 4,
+                ring: RingParams::ML_KEM,
+                hash: Sha3Backend,
             },
             Type::MlKem768 => MLKem {
                 k: 3,
@@ -43,6 +74,8 @@ impl MLKem {
                 eta_2: 2,
                 du: 10,
                 dv: 4,
+                ring: RingParams::ML_KEM,
+                hash: Sha3Backend,
             },
             Type::MlKem1024 => MLKem {
                 k: 4,
@@ -50,93 +83,301 @@ impl MLKem {
                 eta_2: 2,
                 du: 11,
                 dv: 5,
+                ring: RingParams::ML_KEM,
+                hash: Sha3Backend,
             },
         }
     }
 
-    pub fn keygen(&self) -> (Vec<u8>, Vec<u8>) {
-        let d = Self::random_bytes(32);
-        let z = Self::random_bytes(32);
+    /// Builds a user-defined parameter set for research.
+    ///
+    /// The result is **not** a FIPS 203 parameter set and must not be used to
+    /// protect data. `eta_1` and `eta_2` are limited to 1..=3 by the CBD
+    /// sampler and the PRF output size, `du` and `dv` to 1..=11, and `k` to
+    /// 1..=127 so that every PRF nonce fits in a byte.
+    #[cfg(feature = "research")]
+    pub fn custom(k: u8, eta_1: u8, eta_2: u8, du: u8, dv: u8) -> Result<Self, String> {
+        Self::custom_with_ring(RingParams::ML_KEM, k, eta_1, eta_2, du, dv)
+    }
+
+    /// Builds a user-defined parameter set over another ring, such as
+    /// [`RingParams::TOY`]. Keys, ciphertexts and messages shrink with n and
+    /// the bit length of q, and `du` and `dv` must also be below the latter.
+    #[cfg(feature = "research")]
+    pub fn custom_with_ring(
+        ring: RingParams,
+        k: u8,
+        eta_1: u8,
+        eta_2: u8,
+        du: u8,
+        dv: u8,
+    ) -> Result<Self, String> {
+        if !(1..=127).contains(&k) {
+            return Err(String::from("k must be between 1 and 127"));
+        }
+        if !(1..=3).contains(&eta_1) || !(1..=3).contains(&eta_2) {
+            return Err(String::from("eta_1 and eta_2 must be between 1 and 3"));
+        }
+        let max_d = 11.min(ring.bits() as u8 - 1);
+        if !(1..=max_d).contains(&du) || !(1..=max_d).contains(&dv) {
+            return Err(format!("du and dv must be between 1 and {}", max_d));
+        }
+        Ok(MLKem {
+            k,
+            eta_1,
+            eta_2,
+            du,
+            dv,
+            ring,
+            hash: Sha3Backend,
+        })
+    }
+}
+
+impl<H: HashBackend> MLKem<H> {
+    /// The same parameter set hashing through `backend` instead.
+    pub fn with_backend<B: HashBackend>(self, backend: B) -> MLKem<B> {
+        MLKem {
+            k: self.k,
+            eta_1: self.eta_1,
+            eta_2: self.eta_2,
+            du: self.du,
+            dv: self.dv,
+            ring: self.ring,
+            hash: backend,
+        }
+    }
+
+    pub fn backend(&self) -> &H {
+        &self.hash
+    }
+
+    pub fn ring(&self) -> RingParams {
+        self.ring
+    }
+
+    /// Returns the length of an encapsulation key in bytes, `384k + 32` for
+    /// ML-KEM.
+    pub fn encapsulation_key_size(&self) -> usize {
+        self._dk_pke_size() + 32
+    }
+
+    /// Returns the length of a decapsulation key in bytes, `768k + 96` for
+    /// ML-KEM.
+    pub fn decapsulation_key_size(&self) -> usize {
+        2 * self._dk_pke_size() + 96
+    }
+
+    /// Returns the length of a ciphertext in bytes, `32(du k + dv)` for ML-KEM.
+    pub fn ciphertext_size(&self) -> usize {
+        self.ring.n() * (self.du as usize * self.k as usize + self.dv as usize) / 8
+    }
+
+    /// Returns the length of a K-PKE message in bytes, 32 for ML-KEM.
+    pub fn message_size(&self) -> usize {
+        self.ring.n() / 8
+    }
+
+    fn _dk_pke_size(&self) -> usize {
+        self.k as usize * self.ring.polynomial_size()
+    }
+
+    /// Returns the decryption failure probability of the parameter set, see
+    /// [`failure::failure_probability`].
+    pub fn failure_probability(&self) -> Result<f64, String> {
+        self._check_standard_ring()?;
+        failure::failure_probability(self.k, self.eta_1, self.eta_2, self.du, self.dv)
+    }
+
+    /// Returns the core-SVP estimate of the parameter set, see
+    /// [`security::estimate`].
+    pub fn security_estimate(&self) -> Result<security::Estimate, String> {
+        self._check_standard_ring()?;
+        security::estimate(self.k, self.eta_1, self.eta_2, self.du, self.dv)
+    }
 
-        let (ek, dk) = self._keygen_internal(&d, &z);
+    fn _check_standard_ring(&self) -> Result<(), String> {
+        if self.ring != RingParams::ML_KEM {
+            return Err(String::from("only the ML-KEM ring is supported"));
+        }
+        Ok(())
+    }
 
-        (ek, dk)
+    pub fn keygen(&self) -> (Vec<u8>, Vec<u8>) {
+        self.try_keygen().unwrap()
     }
 
     pub fn encaps(&self, ek: &[u8]) -> (Vec<u8>, Vec<u8>) {
-        let m = Self::random_bytes(32);
-        let (k, c) = self._encaps_internal(ek, &m);
-        (k, c)
+        self.try_encaps(ek).unwrap()
     }
 
     pub fn decaps(&self, dk: &[u8], c: &[u8]) -> Vec<u8> {
-        self._decaps_internal(dk, c).unwrap()
+        self.try_decaps(dk, c).unwrap()
+    }
+
+    pub fn try_keygen(&self) -> Result<(Vec<u8>, Vec<u8>), String> {
+        drbg::with_default(|rng| self.try_keygen_with_rng(rng))
+    }
+
+    pub fn try_encaps(&self, ek: &[u8]) -> Result<(Vec<u8>, Vec<u8>), String> {
+        drbg::with_default(|rng| self.try_encaps_with_rng(ek, rng))
+    }
+
+    pub fn try_keygen_with_rng<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+    ) -> Result<(Vec<u8>, Vec<u8>), String> {
+        self_test::check_state()?;
+        // d and z are drawn in a single request, as in the reference
+        // implementation, so that deterministic generators reproduce its KATs.
+        let seed = Self::random_bytes(rng, 64)?;
+        let (d, z) = seed.split_at(32);
+
+        let (ek, dk) = self._keygen_internal(d, z)?;
+        #[cfg(feature = "pairwise-test")]
+        self_test::pairwise_consistency_test(self, &ek, &dk)?;
+
+        Ok((ek, dk))
+    }
+
+    pub fn try_encaps_with_rng<R: RngCore + CryptoRng>(
+        &self,
+        ek: &[u8],
+        rng: &mut R,
+    ) -> Result<(Vec<u8>, Vec<u8>), String> {
+        self_test::check_state()?;
+        let m = Self::random_bytes(rng, self.message_size())?;
+        self._encaps_internal(ek, &m)
+    }
+
+    pub fn try_decaps(&self, dk: &[u8], c: &[u8]) -> Result<Vec<u8>, String> {
+        self_test::check_state()?;
+        self._decaps_internal(dk, c)
     }
 
     fn _decaps_internal(&self, dk: &[u8], c: &[u8]) -> Result<Vec<u8>, String> {
-        if c.len() != 32 * (self.du * self.k + self.dv) as usize {
-            return Err(String::from("ciphertext type check failed"));
-        }
-        if dk.len() != (768_usize * self.k as usize + 96) {
+        self._check_ciphertext(c)?;
+        if dk.len() != self.decapsulation_key_size() {
             return Err(String::from("decapsulation key type check failed"));
         }
 
-        let dk_pke = &dk[0..(384_usize * self.k as usize)];
-        let ek_pke = &dk[(384_usize * self.k as usize)..(768_usize * self.k as usize + 32)];
-        let h = &dk[(768_usize * self.k as usize + 32)..(768_usize * self.k as usize + 64)];
-        let z = &dk[(768_usize * self.k as usize + 64)..];
-
-        if Self::_h(ek_pke) != h {
+        let n = self._dk_pke_size();
+        let dk_pke = &dk[0..n];
+        let ek_pke = &dk[n..(2 * n + 32)];
+        let h = &dk[(2 * n + 32)..(2 * n + 64)];
+        let z = &dk[(2 * n + 64)..];
+
+        let hash_matches = self._h(ek_pke) == h;
+        #[cfg(test)]
+        let hash_matches = fault::flag("hash_check", hash_matches);
+        #[cfg(feature = "fault-hardened")]
+        fault_check(hash_matches == (bytes_diff(&self._h(ek_pke), h) == 0))?;
+        if !hash_matches {
             return Err(String::from("hash check failed"));
         }
 
-        let m_prime = self._k_pke_decrypt(dk_pke, c);
+        let m_prime = self._k_pke_decrypt(dk_pke, c)?;
+        #[cfg(test)]
+        let m_prime = fault::bytes("m_prime", m_prime);
+        #[cfg(feature = "trace")]
+        trace::record("decaps_internal", "m_prime", &m_prime);
 
         let pre_image = [m_prime.clone(), h.to_vec()].concat();
-        let (k_prime, r_prime) = Self::_g(&pre_image);
+        let (k_prime, r_prime) = self._g(&pre_image);
+        #[cfg(test)]
+        let r_prime = fault::bytes("r_prime", r_prime);
         let pre_image = [z, c].concat();
-        let k_bar = Self::_j(&pre_image);
-
-        let c_prime = self._k_pke_encrypt(ek_pke, &m_prime, &r_prime).unwrap();
-
-        Ok(select_bytes(&k_bar, &k_prime, c == c_prime))
+        let k_bar = self._j(&pre_image);
+        #[cfg(feature = "trace")]
+        trace::record("decaps_internal", "K_bar", &k_bar);
+
+        let c_prime = self._k_pke_encrypt(ek_pke, &m_prime, &r_prime)?;
+        #[cfg(feature = "trace")]
+        trace::record("decaps_internal", "c_prime", &c_prime);
+
+        let equal = c == c_prime;
+        #[cfg(test)]
+        let equal = fault::flag("comparison", equal);
+        let k = select_bytes(&k_bar, &k_prime, equal);
+        #[cfg(test)]
+        let k = fault::bytes("select", k);
+        #[cfg(feature = "fault-hardened")]
+        fault_check(k == select_bytes(&k_bar, &k_prime, bytes_diff(c, &c_prime) == 0))?;
+
+        Ok(k)
     }
 
-    fn _encaps_internal(&self, ek: &[u8], m: &[u8]) -> (Vec<u8>, Vec<u8>) {
-        let pre_image = [m, &Self::_h(ek)].concat();
-        let (k, r) = Self::_g(&pre_image);
-        let c = self._k_pke_encrypt(ek, m, &r).unwrap();
-        (k, c)
+    fn _encaps_internal(&self, ek: &[u8], m: &[u8]) -> Result<(Vec<u8>, Vec<u8>), String> {
+        let pre_image = [m, &self._h(ek)].concat();
+        let (k, r) = self._g(&pre_image);
+        let c = self._k_pke_encrypt(ek, m, &r)?;
+        Ok((k, c))
     }
 
-    fn _k_pke_encrypt(&self, ek_pke: &[u8], m: &[u8], r: &[u8]) -> Result<Vec<u8>, String> {
-        if ek_pke.len() != 384 * (self.k as usize) + 32 {
+    fn _check_encapsulation_key(&self, ek: &[u8]) -> Result<Module, String> {
+        if ek.len() != self.encapsulation_key_size() {
             return Err(String::from(
                 "Type check failed, ek_pke has the wrong length",
             ));
         }
-        let t_hat_bytes = &ek_pke[..ek_pke.len() - 32];
-        let rho = &ek_pke[ek_pke.len() - 32..];
-        let t_hat = Module::decode_vector(t_hat_bytes, self.k as usize, 12, true)?;
+        let t_hat_bytes = &ek[..ek.len() - 32];
+        let bits = self.ring.bits();
+        let t_hat = Module::decode_vector(self.ring, t_hat_bytes, self.k as usize, bits, true)?;
 
-        if t_hat.encode(12) != t_hat_bytes {
+        if t_hat.encode(bits) != t_hat_bytes {
             return Err(String::from(
                 "Modulus check failed, t_hat does not encode correctly",
             ));
         }
-        let a_hat_t = self._generate_matrix_from_seed(rho, true);
+        Ok(t_hat)
+    }
+
+    fn _check_ciphertext(&self, c: &[u8]) -> Result<(), String> {
+        if c.len() != self.ciphertext_size() {
+            return Err(String::from("ciphertext type check failed"));
+        }
+        Ok(())
+    }
+
+    fn _check_decapsulation_key(&self, dk: &[u8]) -> Result<(), String> {
+        if dk.len() != self.decapsulation_key_size() {
+            return Err(String::from("decapsulation key type check failed"));
+        }
+        let n = self._dk_pke_size();
+        let ek_pke = &dk[n..(2 * n + 32)];
+        let h = &dk[(2 * n + 32)..(2 * n + 64)];
+        if self._h(ek_pke) != h {
+            return Err(String::from("hash check failed"));
+        }
+        Ok(())
+    }
+
+    fn _k_pke_encrypt(&self, ek_pke: &[u8], m: &[u8], r: &[u8]) -> Result<Vec<u8>, String> {
+        let t_hat = self._check_encapsulation_key(ek_pke)?;
+        let rho = &ek_pke[ek_pke.len() - 32..];
+        let a_hat_t = self._generate_matrix_from_seed(rho, true)?;
         let n = 0;
         let (y, n) = self._generate_error_vector(r, self.eta_1, n);
         let (e_1, n) = self._generate_error_vector(r, self.eta_2, n);
         let (e_2, _) = self._generate_polynomial(r, self.eta_2, n);
+        #[cfg(feature = "trace")]
+        trace::record("k_pke_encrypt", "y", &y);
+        #[cfg(feature = "trace")]
+        trace::record("k_pke_encrypt", "e_1", &e_1);
+        #[cfg(feature = "trace")]
+        trace::record("k_pke_encrypt", "e_2", &e_2);
 
-        let y_hat = y.to_ntt();
+        let y_hat = y.to_ntt()?;
 
         let u = &((a_hat_t.mat_mul(&y_hat)?).from_ntt()) + &e_1;
+        #[cfg(feature = "trace")]
+        trace::record("k_pke_encrypt", "u", &u);
 
-        let mu = Ring::decode(m, 1, false)?.decompress(1);
+        let mu = Ring::decode(self.ring, m, 1, false)?.decompress(1);
 
         let v = &(t_hat.dot(&y_hat)?.from_ntt()) + &(&e_2 + &mu);
+        #[cfg(feature = "trace")]
+        trace::record("k_pke_encrypt", "v", &v);
 
         let c_1 = u.compress(self.du).encode(self.du as usize);
         let c_2 = v.compress(self.dv).encode(self.dv as usize);
@@ -144,137 +385,185 @@ impl MLKem {
         Ok([c_1, c_2].concat())
     }
 
-    fn _k_pke_decrypt(&self, dk_pke: &[u8], c: &[u8]) -> Vec<u8> {
-        let n = self.k as usize * self.du as usize * 32;
+    fn _k_pke_decrypt(&self, dk_pke: &[u8], c: &[u8]) -> Result<Vec<u8>, String> {
+        Ok(self._k_pke_decrypt_w(dk_pke, c)?.compress(1).encode(1))
+    }
+
+    // The noisy message w = v - s^T u before rounding.
+    fn _k_pke_decrypt_w(&self, dk_pke: &[u8], c: &[u8]) -> Result<Ring, String> {
+        let n = self.k as usize * self.du as usize * self.ring.n() / 8;
         let c_1 = &c[..n];
         let c_2 = &c[n..];
-        let u = Module::decode_vector(c_1, self.k as usize, self.du as usize, false)
-            .unwrap()
+        let u = Module::decode_vector(self.ring, c_1, self.k as usize, self.du as usize, false)?
             .decompress(self.du);
-        let v = Ring::decode(c_2, self.dv as usize, false)
-            .unwrap()
-            .decompress(self.dv);
-        let s_hat = Module::decode_vector(dk_pke, self.k as usize, 12, true).unwrap();
-
-        let u_hat = u.to_ntt();
-        let w = &v - &(s_hat.dot(&u_hat).unwrap()).from_ntt();
-
-        w.compress(1).encode(1)
+        #[cfg(test)]
+        let u = u.inject_fault("u");
+        let v = Ring::decode(self.ring, c_2, self.dv as usize, false)?.decompress(self.dv);
+        #[cfg(feature = "trace")]
+        trace::record("k_pke_decrypt", "u", &u);
+        #[cfg(feature = "trace")]
+        trace::record("k_pke_decrypt", "v", &v);
+        let s_hat =
+            Module::decode_vector(self.ring, dk_pke, self.k as usize, self.ring.bits(), true)?;
+
+        let u_hat = u.to_ntt()?;
+        #[cfg(feature = "shuffling")]
+        let w = {
+            let (blind, unblind) = self._blinding_factor();
+            let s_hat = s_hat.scale(blind);
+            &v - &s_hat.dot(&u_hat)?.from_ntt().scale(unblind)
+        };
+        #[cfg(not(feature = "shuffling"))]
+        let w = &v - &s_hat.dot(&u_hat)?.from_ntt();
+        #[cfg(feature = "trace")]
+        trace::record("k_pke_decrypt", "w", &w);
+
+        Ok(w)
     }
 
-    fn _keygen_internal(&self, d: &[u8], z: &[u8]) -> (Vec<u8>, Vec<u8>) {
-        let (ek_pke, dk_pke) = self._k_pke_keygen(d);
+    fn _keygen_internal(&self, d: &[u8], z: &[u8]) -> Result<(Vec<u8>, Vec<u8>), String> {
+        let (ek_pke, dk_pke) = self._k_pke_keygen(d)?;
 
         let ek = ek_pke;
-        let dk = [dk_pke, ek.clone(), Self::_h(&ek), z.to_vec()].concat();
+        let dk = [dk_pke, ek.clone(), self._h(&ek), z.to_vec()].concat();
 
-        (ek, dk)
+        Ok((ek, dk))
     }
 
-    fn _k_pke_keygen(&self, d: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    fn _k_pke_keygen(&self, d: &[u8]) -> Result<(Vec<u8>, Vec<u8>), String> {
         let pre_image: Vec<u8> = [d, &[self.k]].concat();
 
-        let (rho, sigma) = Self::_g(&pre_image);
+        let (rho, sigma) = self._g(&pre_image);
+        #[cfg(feature = "trace")]
+        trace::record("k_pke_keygen", "rho", &rho);
+        #[cfg(feature = "trace")]
+        trace::record("k_pke_keygen", "sigma", &sigma);
 
-        let a_hat = self._generate_matrix_from_seed(&rho, false);
+        let a_hat = self._generate_matrix_from_seed(&rho, false)?;
+        #[cfg(feature = "trace")]
+        trace::record("k_pke_keygen", "A_hat", &a_hat);
 
         let n = 0;
 
         let (s, n) = self._generate_error_vector(&sigma, self.eta_1, n);
 
         let (e, _) = self._generate_error_vector(&sigma, self.eta_1, n);
+        #[cfg(feature = "trace")]
+        trace::record("k_pke_keygen", "s", &s);
+        #[cfg(feature = "trace")]
+        trace::record("k_pke_keygen", "e", &e);
 
-        let s_hat = s.to_ntt();
+        let s_hat = s.to_ntt()?;
 
-        let e_hat = e.to_ntt();
+        let e_hat = e.to_ntt()?;
 
-        let sa_hat = a_hat.mat_mul(&s_hat).unwrap();
+        let sa_hat = a_hat.mat_mul(&s_hat)?;
 
         let t_hat = &sa_hat + &e_hat;
+        #[cfg(test)]
+        let t_hat = t_hat.inject_fault("t_hat");
+        #[cfg(feature = "trace")]
+        trace::record("k_pke_keygen", "t_hat", &t_hat);
+
+        let bits = self.ring.bits();
+        let ek_pke = [t_hat.encode(bits), rho].concat();
+
+        let dk_pke = s_hat.encode(bits);
+
+        // The recheck reuses a_hat and e_hat, so it catches faults in the
+        // product, the sum and the encoding but not in sampling A or e.
+        #[cfg(feature = "fault-hardened")]
+        {
+            let s_hat = Module::decode_vector(self.ring, &dk_pke, self.k as usize, bits, true)?;
+            let t_hat = &a_hat.mat_mul(&s_hat)? + &e_hat;
+            fault_check(t_hat.encode(bits) == ek_pke[..ek_pke.len() - 32])?;
+        }
 
-        let ek_pke = [t_hat.encode(12), rho].concat();
-
-        let dk_pke = s_hat.encode(12);
+        Ok((ek_pke, dk_pke))
+    }
 
-        (ek_pke, dk_pke)
+    // The secret is multiplied by a random nonzero constant before use and the
+    // result by its inverse mod q afterwards.
+    #[cfg(feature = "shuffling")]
+    fn _blinding_factor(&self) -> (usize, usize) {
+        let q = self.ring.q();
+        let r: usize = rand::thread_rng().gen_range(1..q);
+        let r_inv = BigUint::from(r).modpow(&BigUint::from(q - 2), &BigUint::from(q));
+        (r, r_inv.try_into().unwrap())
     }
 
-    fn random_bytes(length: usize) -> Vec<u8> {
+    fn random_bytes<R: RngCore + CryptoRng>(rng: &mut R, length: usize) -> Result<Vec<u8>, String> {
         let mut bytes = vec![0u8; length];
-        OsRng.fill_bytes(&mut bytes);
-        bytes
+        rng.try_fill_bytes(&mut bytes)
+            .map_err(|error| format!("random number generator failed: {}", error))?;
+        Ok(bytes)
     }
 
-    fn _g(s: &[u8]) -> (Vec<u8>, Vec<u8>) {
-        let mut hasher = Sha3_512::new();
-        Update::update(&mut hasher, s);
-        let result = hasher.finalize();
+    fn _g(&self, s: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let result = self.hash.sha3_512(s);
         (result[..32].to_vec(), result[32..].to_vec())
     }
 
-    fn _h(s: &[u8]) -> Vec<u8> {
-        let mut hasher = Sha3_256::new();
-        Update::update(&mut hasher, s);
-        let result = hasher.finalize();
-        result.to_vec()
+    fn _h(&self, s: &[u8]) -> Vec<u8> {
+        self.hash.sha3_256(s).to_vec()
     }
 
-    fn _j(s: &[u8]) -> Vec<u8> {
-        let mut hasher = Shake256::default();
-        hasher.update(s);
-
-        let mut reader = hasher.finalize_xof();
+    fn _j(&self, s: &[u8]) -> Vec<u8> {
         let mut buf = [0u8; 32];
-        reader.read(&mut buf);
+        self.hash.shake256(s, &mut buf);
 
         buf.to_vec()
     }
 
-    fn _xof(b: &[u8], i: u8, j: u8) -> Vec<u8> {
-        // TODO: Add checks
-        let mut hasher = Shake128::default();
+    fn _xof(&self, b: &[u8], i: u8, j: u8) -> H::Shake128Reader {
+        debug_assert_eq!(b.len(), 32, "XOF seed must be 32 bytes");
         let pre_image: Vec<u8> = [b, &[i], &[j]].concat();
-        hasher.update(&pre_image);
-
-        let mut reader = hasher.finalize_xof();
-        let mut buf = [0u8; 840];
-        reader.read(&mut buf);
-
-        buf.to_vec()
+        self.hash.shake128(&pre_image)
     }
 
-    fn _prf(eta: u8, s: &[u8], b: u8) -> Vec<u8> {
-        // TODO: Add checks
-        let mut hasher = Shake256::default();
+    // Returns `length` bytes, the input size of SamplePolyCBD_eta.
+    #[allow(clippy::manual_is_multiple_of)]
+    fn _prf(&self, length: usize, s: &[u8], b: u8) -> Vec<u8> {
+        debug_assert_eq!(s.len(), 32, "PRF seed must be 32 bytes");
+        debug_assert!(length % self.ring.cbd_size(1) == 0);
         let pre_image: Vec<u8> = [s, &[b]].concat();
-        hasher.update(&pre_image);
-
-        let mut reader = hasher.finalize_xof();
-        let mut buf: Vec<u8> = vec![0u8; (eta * 64).into()];
-        reader.read(&mut buf);
+        let mut buf: Vec<u8> = vec![0u8; length];
+        self.hash.shake256(&pre_image, &mut buf);
 
-        buf.to_vec()
+        buf
     }
 
-    fn _generate_matrix_from_seed(&self, rho: &[u8], transpose: bool) -> Module {
+    fn _generate_matrix_from_seed(&self, rho: &[u8], transpose: bool) -> Result<Module, String> {
         let k: usize = self.k.into();
         let mut a_data = vec![vec![Ring::default(); k]; k];
-        for i in 0..k {
-            for j in 0..k {
-                let xof_bytes = Self::_xof(rho, j.try_into().unwrap(), i.try_into().unwrap());
-                a_data[i][j] = Ring::ntt_sample(&xof_bytes);
+        #[cfg(feature = "fault-hardened")]
+        let mut sampled = 0;
+        for (i, row) in a_data.iter_mut().enumerate() {
+            for (j, element) in row.iter_mut().enumerate() {
+                #[cfg(test)]
+                if fault::skip("matrix_sample") {
+                    continue;
+                }
+                let mut xof = self._xof(rho, j.try_into().unwrap(), i.try_into().unwrap());
+                *element = Ring::ntt_sample(self.ring, &mut xof);
+                #[cfg(feature = "fault-hardened")]
+                {
+                    sampled += 1;
+                }
             }
         }
-        Module::new(&a_data, transpose)
+        #[cfg(feature = "fault-hardened")]
+        fault_check(sampled == k * k)?;
+        Ok(Module::new(&a_data, transpose))
     }
 
     fn _generate_error_vector(&self, sigma: &[u8], eta: u8, n: u8) -> (Module, u8) {
         let k: usize = self.k.into();
         let mut elements = vec![Ring::default(); k];
         let mut n = n;
-        for i in 0..k {
-            let prf_output = Self::_prf(eta, sigma, n);
-            elements[i] = Ring::cbd(&prf_output, eta, false).unwrap();
+        for element in elements.iter_mut() {
+            let prf_output = self._prf(self.ring.cbd_size(eta), sigma, n);
+            *element = Ring::cbd(self.ring, &prf_output, eta, false).unwrap();
             n += 1;
         }
         let data = vec![elements];
@@ -282,12 +571,30 @@ impl MLKem {
     }
 
     fn _generate_polynomial(&self, sigma: &[u8], eta: u8, n: u8) -> (Ring, u8) {
-        let prf_output = Self::_prf(eta, sigma, n);
-        let p = Ring::cbd(&prf_output, eta, false).unwrap();
+        let prf_output = self._prf(self.ring.cbd_size(eta), sigma, n);
+        let p = Ring::cbd(self.ring, &prf_output, eta, false).unwrap();
         (p, n + 1)
     }
 }
 
+#[cfg(feature = "fault-hardened")]
+fn bytes_diff(a: &[u8], b: &[u8]) -> u8 {
+    let mut diff = (a.len() != b.len()) as u8;
+    for (x, y) in a.iter().zip(b.iter()) {
+        diff |= x ^ y;
+    }
+    diff
+}
+
+#[cfg(feature = "fault-hardened")]
+fn fault_check(consistent: bool) -> Result<(), String> {
+    if consistent {
+        Ok(())
+    } else {
+        Err(String::from(FAULT_DETECTED))
+    }
+}
+
 fn select_bytes(a: &[u8], b: &[u8], cond: bool) -> Vec<u8> {
     // TODO: Add checks
     let mut out = vec![0_u8; a.len()];
@@ -298,22 +605,77 @@ fn select_bytes(a: &[u8], b: &[u8], cond: bool) -> Vec<u8> {
     out
 }
 
+pub const FAULT_DETECTED: &str = "fault detected";
+
 pub const ML_KEM_512: MLKem = MLKem::new(Type::MlKem512);
 pub const ML_KEM_768: MLKem = MLKem::new(Type::MlKem768);
 pub const ML_KEM_1024: MLKem = MLKem::new(Type::MlKem1024);
 
+/// A miniature, insecure ML-KEM over [`RingParams::TOY`] with k = 1, for
+/// teaching and testing over every message. Messages are 2 bytes and
+/// ciphertexts 24.
+#[cfg(feature = "research")]
+pub const TOY_ML_KEM: MLKem = MLKem {
+    k: 1,
+    eta_1: 1,
+    eta_2: 1,
+    du: 8,
+    dv: 4,
+    ring: RingParams::TOY,
+    hash: Sha3Backend,
+};
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use serde_json::Value;
-    use std::fs;
+    use std::{cell::RefCell, fs};
+
+    // Forwards to the sha3 crate and records which function was called.
+    #[derive(Default)]
+    pub(crate) struct Recording {
+        calls: RefCell<Vec<&'static str>>,
+    }
+
+    impl Recording {
+        pub(crate) fn count(&self, function: &str) -> usize {
+            self.calls
+                .borrow()
+                .iter()
+                .filter(|&&x| x == function)
+                .count()
+        }
+    }
+
+    impl HashBackend for Recording {
+        type Shake128Reader = <Sha3Backend as HashBackend>::Shake128Reader;
+
+        fn sha3_256(&self, input: &[u8]) -> [u8; 32] {
+            self.calls.borrow_mut().push("SHA3-256");
+            Sha3Backend.sha3_256(input)
+        }
+
+        fn sha3_512(&self, input: &[u8]) -> [u8; 64] {
+            self.calls.borrow_mut().push("SHA3-512");
+            Sha3Backend.sha3_512(input)
+        }
 
-    fn keygen_kat(_type: Type, index: usize) {
+        fn shake128(&self, input: &[u8]) -> Self::Shake128Reader {
+            self.calls.borrow_mut().push("SHAKE128");
+            Sha3Backend.shake128(input)
+        }
+
+        fn shake256(&self, input: &[u8], output: &mut [u8]) {
+            self.calls.borrow_mut().push("SHAKE256");
+            Sha3Backend.shake256(input, output)
+        }
+    }
+
+    fn keygen_kat<H: HashBackend>(ml_kem: &MLKem<H>, index: usize) {
         let data =
             fs::read_to_string("assets/ML-KEM-keyGen-FIPS203/internalProjection.json").unwrap();
         let json: Value = serde_json::from_str(&data).unwrap();
         let tests = json["testGroups"][index]["tests"].as_array().unwrap();
-        let ml_kem = MLKem::new(_type);
         for value in tests.iter() {
             let z = &value["z"];
             let d = &value["d"];
@@ -323,7 +685,7 @@ mod tests {
             let z_as_bytes = hex::decode(z.as_str().unwrap()).unwrap();
             let d_as_bytes = hex::decode(d.as_str().unwrap()).unwrap();
 
-            let (actual_ek, actual_dk) = ml_kem._keygen_internal(&d_as_bytes, &z_as_bytes);
+            let (actual_ek, actual_dk) = ml_kem._keygen_internal(&d_as_bytes, &z_as_bytes).unwrap();
 
             let ek_as_bytes = hex::decode(ek.as_str().unwrap()).unwrap();
             let dk_as_bytes = hex::decode(dk.as_str().unwrap()).unwrap();
@@ -333,12 +695,11 @@ mod tests {
         }
     }
 
-    fn encaps_kat(_type: Type, index: usize) {
+    fn encaps_kat<H: HashBackend>(ml_kem: &MLKem<H>, index: usize) {
         let data =
             fs::read_to_string("assets/ML-KEM-encapDecap-FIPS203/internalProjection.json").unwrap();
         let json: Value = serde_json::from_str(&data).unwrap();
         let tests = json["testGroups"][index]["tests"].as_array().unwrap();
-        let ml_kem = MLKem::new(_type);
         for value in tests.iter() {
             let c = &value["c"];
             let k = &value["k"];
@@ -349,7 +710,7 @@ mod tests {
             let ek_as_bytes = hex::decode(ek.as_str().unwrap()).unwrap();
             let m_as_bytes = hex::decode(m.as_str().unwrap()).unwrap();
 
-            let (actual_k, actual_c) = ml_kem._encaps_internal(&ek_as_bytes, &m_as_bytes);
+            let (actual_k, actual_c) = ml_kem._encaps_internal(&ek_as_bytes, &m_as_bytes).unwrap();
 
             let k_as_bytes = hex::decode(k.as_str().unwrap()).unwrap();
             let c_as_bytes = hex::decode(c.as_str().unwrap()).unwrap();
@@ -364,14 +725,13 @@ mod tests {
         }
     }
 
-    fn decaps_kat(_type: Type, index: usize) {
+    fn decaps_kat<H: HashBackend>(ml_kem: &MLKem<H>, index: usize) {
         let data =
             fs::read_to_string("assets/ML-KEM-encapDecap-FIPS203/internalProjection.json").unwrap();
         let json: Value = serde_json::from_str(&data).unwrap();
         let kat_data = json["testGroups"][3 + index]["tests"].as_array().unwrap();
         let dk = json["testGroups"][3 + index]["dk"].as_str().unwrap();
         let dk_as_bytes = hex::decode(dk).unwrap();
-        let ml_kem = MLKem::new(_type);
         for value in kat_data.iter() {
             let c = &value["c"];
             let c_as_bytes = hex::decode(c.as_str().unwrap()).unwrap();
@@ -382,24 +742,159 @@ mod tests {
         }
     }
 
+    #[test]
+    fn sizes() {
+        assert_eq!(ML_KEM_512.encapsulation_key_size(), 800);
+        assert_eq!(ML_KEM_512.decapsulation_key_size(), 1632);
+        assert_eq!(ML_KEM_512.ciphertext_size(), 768);
+        assert_eq!(ML_KEM_768.encapsulation_key_size(), 1184);
+        assert_eq!(ML_KEM_768.decapsulation_key_size(), 2400);
+        assert_eq!(ML_KEM_768.ciphertext_size(), 1088);
+        assert_eq!(ML_KEM_1024.encapsulation_key_size(), 1568);
+        assert_eq!(ML_KEM_1024.decapsulation_key_size(), 3168);
+        assert_eq!(ML_KEM_1024.ciphertext_size(), 1568);
+    }
+
+    #[cfg(feature = "research")]
+    #[test]
+    fn custom_parameter_set() {
+        let ml_kem = MLKem::custom(5, 1, 1, 11, 5).unwrap();
+        let (ek, dk) = ml_kem.keygen();
+        assert_eq!(ek.len(), ml_kem.encapsulation_key_size());
+        assert_eq!(dk.len(), ml_kem.decapsulation_key_size());
+        let (key, c) = ml_kem.encaps(&ek);
+        assert_eq!(c.len(), ml_kem.ciphertext_size());
+        assert_eq!(c.len(), 1920);
+        assert_eq!(ml_kem.decaps(&dk, &c), key);
+
+        assert!(MLKem::custom(0, 2, 2, 10, 4).is_err());
+        assert!(MLKem::custom(128, 2, 2, 10, 4).is_err());
+        assert!(MLKem::custom(3, 4, 2, 10, 4).is_err());
+        assert!(MLKem::custom(3, 2, 0, 10, 4).is_err());
+        assert!(MLKem::custom(3, 2, 2, 12, 4).is_err());
+        assert!(MLKem::custom(3, 2, 2, 10, 0).is_err());
+    }
+
+    #[cfg(feature = "research")]
+    // Messages 0, step, 2 step, ... up to 2^16 under `key_pairs` key pairs
+    // generated from fixed seeds.
+    fn toy_round_trips(key_pairs: u8, step: usize) {
+        let ml_kem = TOY_ML_KEM;
+        for seed in 0..key_pairs {
+            let (ek, dk) = ml_kem._keygen_internal(&[seed; 32], &[!seed; 32]).unwrap();
+            for m in (0..=u16::MAX).step_by(step) {
+                let (key, c) = ml_kem._encaps_internal(&ek, &m.to_le_bytes()).unwrap();
+                assert_eq!(ml_kem._decaps_internal(&dk, &c).unwrap(), key);
+            }
+        }
+    }
+
+    #[cfg(feature = "research")]
+    #[test]
+    fn toy_parameter_set() {
+        let ml_kem = TOY_ML_KEM;
+        assert_eq!(ml_kem.encapsulation_key_size(), 50);
+        assert_eq!(ml_kem.decapsulation_key_size(), 132);
+        assert_eq!(ml_kem.ciphertext_size(), 24);
+        assert_eq!(ml_kem.message_size(), 2);
+        assert!(ml_kem.failure_probability().is_err());
+        assert!(ml_kem.security_estimate().is_err());
+
+        let (ek, dk) = ml_kem.keygen();
+        assert_eq!(ek.len(), 50);
+        assert_eq!(dk.len(), 132);
+        let (key, c) = ml_kem.encaps(&ek);
+        assert_eq!(c.len(), 24);
+        assert_eq!(ml_kem.decaps(&dk, &c), key);
+        toy_round_trips(1, 251);
+        tiny_ciphertexts(1, 251);
+
+        let ring = RingParams::TOY;
+        assert!(MLKem::custom_with_ring(ring, 2, 1, 1, 8, 4).is_ok());
+        assert!(MLKem::custom_with_ring(ring, 2, 1, 1, 9, 4).is_err());
+        assert!(MLKem::custom_with_ring(ring, 2, 1, 1, 8, 9).is_err());
+    }
+
+    // Ciphertexts 0, step, 2 step, ... up to 2^16 of the scheme over the
+    // smallest supported ring, n = 8 and q = 17, with k = 1 and du = dv = 1, so
+    // that ciphertexts are 2 bytes. A ciphertext must only be accepted if it
+    // is the encryption of one of the 256 messages, with that message's key.
+    // Most honest ciphertexts are rejected too, as 1-bit compression leaves
+    // too much noise to decrypt.
+    #[cfg(feature = "research")]
+    fn tiny_ciphertexts(key_pairs: u8, step: usize) {
+        let ring = RingParams::new(8, 17, 2).unwrap();
+        let ml_kem = MLKem::custom_with_ring(ring, 1, 1, 1, 1, 1).unwrap();
+        assert_eq!(ml_kem.ciphertext_size(), 2);
+        for seed in 0..key_pairs {
+            let (ek, dk) = ml_kem._keygen_internal(&[seed; 32], &[!seed; 32]).unwrap();
+            let z = &dk[dk.len() - 32..];
+            let mut honest = std::collections::HashMap::new();
+            for m in 0..=u8::MAX {
+                let (key, c) = ml_kem._encaps_internal(&ek, &[m]).unwrap();
+                honest.entry(c).or_insert_with(Vec::new).push(key);
+            }
+            for c in (0..=u16::MAX).step_by(step) {
+                let c = c.to_le_bytes();
+                let key = ml_kem._decaps_internal(&dk, &c).unwrap();
+                if key != ml_kem._j(&[z, &c].concat()) {
+                    assert!(honest.get(&c[..]).is_some_and(|keys| keys.contains(&key)));
+                }
+            }
+        }
+    }
+
+    // Every one of the 2^16 messages of the toy scheme round-trips under each
+    // of 16 key pairs, and every one of the 2^16 ciphertexts of the n = 8
+    // scheme is checked under 16 key pairs. Toy ciphertexts have 2^192 values
+    // and ciphertexts over n = 16 at least 2^32, and keys come from 64-byte
+    // seeds, with 3^16 secret and error vectors even for n = 8, so these are
+    // not enumerated.
+    #[cfg(feature = "research")]
+    #[test]
+    #[ignore]
+    fn toy_exhaustive() {
+        toy_round_trips(16, 1);
+        tiny_ciphertexts(16, 1);
+    }
+
+    const PARAMETER_SETS: [MLKem; 3] = [ML_KEM_512, ML_KEM_768, ML_KEM_1024];
+
     #[test]
     fn test_keygen_using_kat() {
-        keygen_kat(Type::MlKem512, 0);
-        keygen_kat(Type::MlKem768, 1);
-        keygen_kat(Type::MlKem1024, 2);
+        for (index, ml_kem) in PARAMETER_SETS.into_iter().enumerate() {
+            keygen_kat(&ml_kem, index);
+            keygen_kat(&ml_kem.with_backend(Recording::default()), index);
+        }
     }
 
     #[test]
     fn test_encaps_using_kat() {
-        encaps_kat(Type::MlKem512, 0);
-        encaps_kat(Type::MlKem768, 1);
-        encaps_kat(Type::MlKem1024, 2);
+        for (index, ml_kem) in PARAMETER_SETS.into_iter().enumerate() {
+            encaps_kat(&ml_kem, index);
+            encaps_kat(&ml_kem.with_backend(Recording::default()), index);
+        }
     }
 
     #[test]
     fn test_decaps_using_kat() {
-        decaps_kat(Type::MlKem512, 0);
-        decaps_kat(Type::MlKem768, 1);
-        decaps_kat(Type::MlKem1024, 2);
+        for (index, ml_kem) in PARAMETER_SETS.into_iter().enumerate() {
+            decaps_kat(&ml_kem, index);
+            decaps_kat(&ml_kem.with_backend(Recording::default()), index);
+        }
+    }
+
+    #[test]
+    fn recording_backend() {
+        let ml_kem = ML_KEM_768.with_backend(Recording::default());
+        ml_kem._keygen_internal(&[1; 32], &[2; 32]).unwrap();
+        let backend = ml_kem.backend();
+        // G(d || k), A_hat, s and e, and H(ek).
+        assert_eq!(backend.count("SHA3-512"), 1);
+        assert_eq!(backend.count("SHAKE128"), 9);
+        assert_eq!(backend.count("SHAKE256"), 6);
+        assert_eq!(backend.count("SHA3-256"), 1);
+
+        self_test::known_answer_tests(&ml_kem).unwrap();
     }
 }
@@ -1,18 +1,24 @@
+use rand::Rng;
 use std::{
     fmt::Debug,
-    ops::{Add, Index},
+    ops::{Add, AddAssign, Index, Neg, Sub, SubAssign},
 };
 
-use crate::ring::Ring;
+use crate::ring::{Ring, RingParams};
+#[cfg(feature = "trace")]
+use crate::trace::Intermediate;
 
-#[derive(Clone, PartialEq)]
+/// A matrix of polynomials. A column vector of k polynomials is a k x 1
+/// matrix, stored as one row with `transpose` set.
+#[derive(Clone)]
 pub struct Module {
     data: Vec<Vec<Ring>>,
     transpose: bool,
 }
 
 impl Module {
-    pub fn new(data: &Vec<Vec<Ring>>, transpose: bool) -> Self {
+    /// A matrix from its rows, or from its columns if `transpose` is set.
+    pub fn new(data: &[Vec<Ring>], transpose: bool) -> Self {
         Self {
             data: data.to_vec(),
             transpose,
@@ -20,66 +26,87 @@ impl Module {
     }
 
     pub fn random(m: usize, n: usize) -> Self {
+        Self::random_with_rng(RingParams::ML_KEM, &mut rand::thread_rng(), m, n, false)
+    }
+
+    /// An m x n matrix of polynomials with coefficients uniform in [0, q).
+    pub fn random_with_rng<R: Rng + ?Sized>(
+        params: RingParams,
+        rng: &mut R,
+        m: usize,
+        n: usize,
+        is_ntt: bool,
+    ) -> Self {
         let mut data = vec![];
         for _ in 0..m {
             let mut row = vec![];
             for _ in 0..n {
-                row.push(Ring::random());
+                row.push(Ring::random_with_rng(params, rng, is_ntt));
             }
             data.push(row);
         }
         Self::new(&data, false)
     }
 
+    /// The matrix product, with entries in the domain of the operands.
     pub fn mat_mul(&self, rhs: &Self) -> Result<Self, String> {
-        // TODO: Add checks
         let (m_1, n_1) = self.dim();
         let (m_2, n_2) = rhs.dim();
         if n_1 != m_2 {
             return Err(String::from("Invalid dimensions"));
         }
-        let mut new_data = vec![vec![Ring::zero(); n_2]; m_1];
+        // The sums stay in the domain of the operands.
+        let zero = Ring::zero_in_domain(self[(0, 0)].params(), self[(0, 0)].is_ntt());
+        let mut new_data = vec![vec![zero; n_2]; m_1];
         for i in 0..m_1 {
             for j in 0..n_2 {
                 for k in 0..n_1 {
-                    new_data[i][j] += (&self[(i, k)] * &rhs[(k, j)]).unwrap();
+                    #[cfg(test)]
+                    if crate::fault::skip("mat_mul_term") {
+                        continue;
+                    }
+                    new_data[i][j] += (&self[(i, k)] * &rhs[(k, j)])?;
                 }
             }
         }
         Ok(Module::new(&new_data, false))
     }
 
+    /// The number of rows and columns.
     pub fn dim(&self) -> (usize, usize) {
         if self.transpose {
-            return (self.data[0].len(), self.data.len());
+            (self.data[0].len(), self.data.len())
         } else {
-            return (self.data.len(), self.data[0].len());
+            (self.data.len(), self.data[0].len())
         }
     }
 
+    /// The inner product `self^T rhs` of two column vectors.
     pub fn dot(&self, rhs: &Self) -> Result<Ring, String> {
-        // TODO: Add checks
         let transposed = Module::new(&self.data, !self.transpose);
-        let res = transposed.mat_mul(rhs).unwrap();
+        let res = transposed.mat_mul(rhs)?;
         if res.dim() != (1, 1) {
-            return Err(String::from("Invalid response"));
+            Err(String::from("Invalid response"))
         } else {
-            return Ok(res[(0, 0)].clone());
+            Ok(res[(0, 0)].clone())
         }
     }
 
-    pub fn to_ntt(&self) -> Self {
+    /// The NTT of every entry.
+    pub fn to_ntt(&self) -> Result<Self, String> {
         let mut data = vec![];
         for row in self.data.iter() {
             let mut new_row = vec![];
             for element in row {
-                new_row.push(element.to_ntt());
+                new_row.push(element.to_ntt()?);
             }
             data.push(new_row);
         }
-        Module::new(&data, self.transpose)
+        Ok(Module::new(&data, self.transpose))
     }
 
+    /// The inverse NTT of every entry.
+    #[allow(clippy::wrong_self_convention)]
     pub fn from_ntt(&self) -> Self {
         let mut data = vec![];
         for row in self.data.iter() {
@@ -92,6 +119,40 @@ impl Module {
         Module::new(&data, self.transpose)
     }
 
+    /// Multiplies every entry by the polynomial `f`, which must be in the
+    /// domain of the entries.
+    pub fn mul_ring(&self, f: &Ring) -> Result<Self, String> {
+        let mut new_data = vec![];
+        for row in self.data.iter() {
+            let mut new_row = vec![];
+            for ele in row {
+                new_row.push((ele * f)?);
+            }
+            new_data.push(new_row);
+        }
+        Ok(Module::new(&new_data, self.transpose))
+    }
+
+    /// Iterates over the entries row by row.
+    pub fn iter(&self) -> impl Iterator<Item = &Ring> {
+        let (m, n) = self.dim();
+        (0..m).flat_map(move |i| (0..n).map(move |j| &self[(i, j)]))
+    }
+
+    /// Multiplies every coefficient by the scalar `c` mod q.
+    pub fn scale(&self, c: usize) -> Self {
+        let mut new_data = vec![];
+        for row in self.data.iter() {
+            let mut new_row = vec![];
+            for ele in row {
+                new_row.push(ele.scale(c));
+            }
+            new_data.push(new_row);
+        }
+        Module::new(&new_data, self.transpose)
+    }
+
+    /// The concatenated ByteEncode_d of every entry.
     pub fn encode(&self, d: usize) -> Vec<u8> {
         let mut output = vec![];
         for row in self.data.iter() {
@@ -103,26 +164,30 @@ impl Module {
         output
     }
 
+    /// Decodes a column vector of k polynomials encoded with d bits per
+    /// coefficient.
     pub fn decode_vector(
+        params: RingParams,
         input_bytes: &[u8],
         k: usize,
         d: usize,
         is_ntt: bool,
     ) -> Result<Self, String> {
-        if (256 * d * k) != input_bytes.len() * 8 {
+        if (params.n() * d * k) != input_bytes.len() * 8 {
             return Err(String::from(
                 "Byte length is the wrong length for given k, d values",
             ));
         }
-        let n = 32 * d;
+        let n = params.n() * d / 8;
         let mut data = vec![];
         for i in (0..input_bytes.len()).step_by(n) {
-            let ring = Ring::decode(&input_bytes[i..(i + n)], d, is_ntt).unwrap();
+            let ring = Ring::decode(params, &input_bytes[i..(i + n)], d, is_ntt).unwrap();
             data.push(ring);
         }
-        Ok(Module::new(&vec![data], true))
+        Ok(Module::new(&[data], true))
     }
 
+    /// Compress_d of every entry.
     pub fn compress(&self, d: u8) -> Self {
         let mut new_data = vec![];
         for row in self.data.iter() {
@@ -136,6 +201,7 @@ impl Module {
         Module::new(&new_data, self.transpose)
     }
 
+    /// Decompress_d of every entry.
     pub fn decompress(&self, d: u8) -> Self {
         let mut new_data = vec![];
         for row in self.data.iter() {
@@ -149,27 +215,102 @@ impl Module {
         Module::new(&new_data, self.transpose)
     }
 
+    #[cfg(test)]
+    pub fn inject_fault(&self, point: &'static str) -> Self {
+        let mut new_data = vec![];
+        for row in self.data.iter() {
+            let mut new_row = vec![];
+            for ele in row {
+                new_row.push(ele.inject_fault(point));
+            }
+            new_data.push(new_row);
+        }
+        Module::new(&new_data, self.transpose)
+    }
+
     pub fn transpose(&self) -> bool {
         self.transpose
     }
+
+    // Applies `f` to matching entries, keeping the layout of `self`.
+    fn _entrywise(&self, rhs: &Self, f: impl Fn(&Ring, &Ring) -> Ring) -> Self {
+        assert_eq!(self.dim(), rhs.dim(), "Invalid dimensions");
+        let mut new_data = vec![];
+        for (r, row) in self.data.iter().enumerate() {
+            let mut new_row = vec![];
+            for (c, ele) in row.iter().enumerate() {
+                let index = if self.transpose { (c, r) } else { (r, c) };
+                new_row.push(f(ele, &rhs[index]));
+            }
+            new_data.push(new_row);
+        }
+        Module::new(&new_data, self.transpose)
+    }
+}
+
+// Column vectors are written as a flat array of polynomials.
+#[cfg(feature = "trace")]
+impl Intermediate for Module {
+    fn to_json(&self) -> serde_json::Value {
+        let (m, n) = self.dim();
+        let rows: Vec<serde_json::Value> = (0..m)
+            .map(|i| match n {
+                1 => self[(i, 0)].to_json(),
+                _ => serde_json::Value::from(
+                    (0..n).map(|j| self[(i, j)].to_json()).collect::<Vec<_>>(),
+                ),
+            })
+            .collect();
+        serde_json::Value::from(rows)
+    }
 }
 
 impl Add for &Module {
     type Output = Module;
 
     fn add(self, rhs: Self) -> Self::Output {
-        // TODO: Add checks
-        let (m, n) = self.dim();
+        self._entrywise(rhs, |x, y| x + y)
+    }
+}
+
+impl AddAssign for Module {
+    fn add_assign(&mut self, rhs: Self) {
+        let new_module = &*self + &rhs;
+        *self = new_module;
+    }
+}
+
+impl Sub for &Module {
+    type Output = Module;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self._entrywise(rhs, |x, y| x - y)
+    }
+}
+
+impl SubAssign for Module {
+    fn sub_assign(&mut self, rhs: Self) {
+        let new_module = &*self - &rhs;
+        *self = new_module;
+    }
+}
+
+impl Neg for &Module {
+    type Output = Module;
+
+    fn neg(self) -> Self::Output {
         let mut new_data = vec![];
-        for i in 0..m {
-            let mut new_row = vec![];
-            for j in 0..n {
-                let new_element = &self[(i, j)] + &rhs[(i, j)];
-                new_row.push(new_element);
-            }
-            new_data.push(new_row);
+        for row in self.data.iter() {
+            new_data.push(row.iter().map(|ele| -ele).collect());
         }
-        Module::new(&new_data, false)
+        Module::new(&new_data, self.transpose)
+    }
+}
+
+// Equal when the dimensions and entries are, however they are stored.
+impl PartialEq for Module {
+    fn eq(&self, other: &Self) -> bool {
+        self.dim() == other.dim() && self.iter().eq(other.iter())
     }
 }
 
@@ -195,11 +336,137 @@ impl Index<(usize, usize)> for Module {
     }
 }
 
+#[cfg(test)]
 mod tests {
     use crate::ring::Ring;
 
     use super::Module;
 
+    const CASES: usize = 8;
+
+    fn random_ntt(m: usize, n: usize) -> Module {
+        Module::random(m, n).to_ntt().unwrap()
+    }
+
+    #[test]
+    fn mat_mul_distributivity() {
+        for _ in 0..CASES {
+            let a = random_ntt(3, 2);
+            let b = random_ntt(3, 2);
+            let c = random_ntt(2, 4);
+            let d = random_ntt(2, 4);
+            let product = |x: &Module, y: &Module| x.mat_mul(y).unwrap();
+
+            assert_eq!(product(&a, &(&c + &d)), &product(&a, &c) + &product(&a, &d));
+            assert_eq!(product(&(&a + &b), &c), &product(&a, &c) + &product(&b, &c));
+            assert_eq!(product(&a, &c).dim(), (3, 4));
+        }
+    }
+
+    #[test]
+    fn dot_properties() {
+        for _ in 0..CASES {
+            let x = random_ntt(3, 1);
+            let y = random_ntt(3, 1);
+            let z = random_ntt(3, 1);
+
+            assert_eq!(x.dot(&y).unwrap(), y.dot(&x).unwrap());
+            assert_eq!(
+                x.dot(&(&y + &z)).unwrap(),
+                &x.dot(&y).unwrap() + &x.dot(&z).unwrap()
+            );
+            let mut expected = Ring::zero().to_ntt().unwrap();
+            for i in 0..3 {
+                expected += (&x[(i, 0)] * &y[(i, 0)]).unwrap();
+            }
+            assert_eq!(x.dot(&y).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn group_operations() {
+        for _ in 0..CASES {
+            let a = random_ntt(3, 2);
+            let b = random_ntt(3, 2);
+            let zero = &a - &a;
+            assert_eq!(&(&a - &b) + &b, a);
+            assert_eq!(&-&a + &a, zero);
+
+            let mut c = a.clone();
+            c += b.clone();
+            assert_eq!(c, &a + &b);
+            c -= b.clone();
+            assert_eq!(c, a);
+
+            let f = Ring::random().to_ntt().unwrap();
+            let scaled = a.mul_ring(&f).unwrap();
+            for i in 0..3 {
+                for j in 0..2 {
+                    assert_eq!(scaled[(i, j)], (&a[(i, j)] * &f).unwrap());
+                }
+            }
+            assert!(a.mul_ring(&Ring::random()).is_err());
+            let entries: Vec<&Ring> = a.iter().collect();
+            assert_eq!(entries.len(), 6);
+            assert_eq!(entries[3], &a[(1, 1)]);
+        }
+    }
+
+    #[test]
+    fn transposed_vectors() {
+        let column = |x: Module| Module::new(&[x.iter().cloned().collect()], true);
+        for _ in 0..CASES {
+            let a = column(random_ntt(3, 1));
+            let b = column(random_ntt(3, 1));
+            assert_eq!(a.dim(), (3, 1));
+
+            let difference = &a - &b;
+            assert!(difference.transpose());
+            assert_eq!(&difference + &b, a);
+            assert!((&difference + &b).transpose());
+
+            let mut c = a.clone();
+            c += b.clone();
+            assert!(c.transpose());
+            c -= b.clone();
+            assert_eq!(c, a);
+
+            // The same vector stored as three rows.
+            let rows = Module::new(
+                &[
+                    vec![a[(0, 0)].clone()],
+                    vec![a[(1, 0)].clone()],
+                    vec![a[(2, 0)].clone()],
+                ],
+                false,
+            );
+            assert_eq!(rows, a);
+            assert_eq!(&a - &rows, &b - &b);
+            assert_ne!(rows, Module::new(&[rows.iter().cloned().collect()], false));
+        }
+    }
+
+    #[test]
+    fn shape_errors() {
+        let a = random_ntt(3, 2);
+        let b = random_ntt(3, 2);
+        assert!(a.mat_mul(&b).is_err());
+        assert!(random_ntt(2, 1).dot(&random_ntt(3, 1)).is_err());
+        assert!(a.dot(&b).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid dimensions")]
+    fn add_checks_shape() {
+        let _ = &random_ntt(3, 2) + &random_ntt(2, 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid dimensions")]
+    fn sub_checks_shape() {
+        let _ = &random_ntt(3, 1) - &random_ntt(2, 1);
+    }
+
     #[test]
     #[ignore]
     fn mat_mul() {
@@ -207,14 +474,14 @@ mod tests {
         let zero = Ring::zero();
         let one = Ring::one();
         let zero_module = Module::new(
-            &vec![
+            &[
                 vec![zero.clone(), zero.clone()],
                 vec![zero.clone(), zero.clone()],
             ],
             false,
         );
         let identity_module = Module::new(
-            &vec![vec![one.clone(), zero.clone()], vec![zero.clone(), one]],
+            &[vec![one.clone(), zero.clone()], vec![zero.clone(), one]],
             false,
         );
         for _ in 0..10 {
@@ -223,7 +490,7 @@ mod tests {
             let c = Module::random(2, 2);
             let random_ring = Ring::random();
             let d = Module::new(
-                &vec![
+                &[
                     vec![random_ring.clone(), zero.clone()],
                     vec![zero.clone(), random_ring],
                 ],
@@ -0,0 +1,128 @@
+// Negative tests for malformed keys and ciphertexts. The Wycheproof-style
+// vectors in `assets/negative` were generated with this crate, so they only pin
+// its exact errors and check that invalid ciphertexts of the right length yield
+// J(z || c). The upstream Wycheproof vectors in `assets/wycheproof` are the
+// independent check, and only distinguish valid and invalid inputs.
+
+use std::fs;
+
+use serde_json::Value;
+
+use crate::acvp;
+
+fn bytes(test: &Value, name: &str) -> Vec<u8> {
+    hex::decode(test[name].as_str().unwrap()).unwrap()
+}
+
+// Each test either expects the exact error returned, or the shared key.
+fn check(test: &Value, result: Result<Vec<u8>, String>) {
+    let tc_id = &test["tcId"];
+    match test["result"].as_str().unwrap() {
+        "valid" => assert_eq!(result, Ok(bytes(test, "K")), "tcId {}", tc_id),
+        _ => assert_eq!(
+            result,
+            Err(test["error"].as_str().unwrap().to_string()),
+            "tcId {}",
+            tc_id
+        ),
+    }
+}
+
+fn check_file(path: &str) {
+    let json: Value = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+    let mut count = 0;
+    for group in json["testGroups"].as_array().unwrap() {
+        let ml_kem = acvp::parameter_set(group["parameterSet"].as_str().unwrap()).unwrap();
+        for test in group["tests"].as_array().unwrap() {
+            match group["type"].as_str().unwrap() {
+                "MLKEMEncapsTest" => {
+                    let result = ml_kem._encaps_internal(&bytes(test, "ek"), &bytes(test, "m"));
+                    if let Ok((_, c)) = &result {
+                        assert_eq!(c, &bytes(test, "c"), "tcId {}", test["tcId"]);
+                    }
+                    check(test, result.map(|(k, _)| k));
+                }
+                _ => {
+                    let dk = bytes(test, "dk");
+                    let c = bytes(test, "c");
+                    let result = ml_kem.try_decaps(&dk, &c);
+                    if test["flags"]
+                        .as_array()
+                        .unwrap()
+                        .contains(&Value::from("ImplicitRejection"))
+                    {
+                        let z = &dk[dk.len() - 32..];
+                        assert_eq!(bytes(test, "K"), ml_kem._j(&[z, &c].concat()));
+                    }
+                    check(test, result);
+                }
+            }
+            count += 1;
+        }
+    }
+    assert_eq!(json["numberOfTests"], count);
+}
+
+// Runs an upstream Wycheproof file. Valid tests must produce `c` and `K`, and
+// invalid ones any error.
+fn check_wycheproof_file(path: &str) {
+    let json: Value = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+    let mut count = 0;
+    for group in json["testGroups"].as_array().unwrap() {
+        let ml_kem = acvp::parameter_set(group["parameterSet"].as_str().unwrap()).unwrap();
+        for test in group["tests"].as_array().unwrap() {
+            let result = match group["type"].as_str().unwrap() {
+                "MLKEMEncapsTest" => {
+                    let result = ml_kem._encaps_internal(&bytes(test, "ek"), &bytes(test, "m"));
+                    result.map(|(k, c)| {
+                        assert_eq!(c, bytes(test, "c"), "tcId {}", test["tcId"]);
+                        k
+                    })
+                }
+                "MLKEMDecapsValidationTest" => {
+                    ml_kem.try_decaps(&bytes(test, "dk"), &bytes(test, "c"))
+                }
+                group_type => panic!("unsupported test group {}", group_type),
+            };
+            let expected = match test["result"].as_str().unwrap() {
+                "valid" => Some(bytes(test, "K")),
+                _ => None,
+            };
+            assert_eq!(
+                result.ok(),
+                expected,
+                "tcId {}: {}",
+                test["tcId"],
+                test["comment"]
+            );
+            count += 1;
+        }
+    }
+    assert_eq!(json["numberOfTests"], count);
+}
+
+#[test]
+fn ml_kem_512_negative() {
+    check_file("assets/negative/mlkem_512_test.json");
+}
+
+#[test]
+fn ml_kem_768_negative() {
+    check_file("assets/negative/mlkem_768_test.json");
+}
+
+#[test]
+fn ml_kem_1024_negative() {
+    check_file("assets/negative/mlkem_1024_test.json");
+}
+
+#[test]
+fn wycheproof() {
+    for n in [512, 768, 1024] {
+        check_wycheproof_file(&format!("assets/wycheproof/mlkem_{}_encaps_test.json", n));
+        check_wycheproof_file(&format!(
+            "assets/wycheproof/mlkem_{}_semi_expanded_decaps_test.json",
+            n
+        ));
+    }
+}
@@ -0,0 +1,106 @@
+//! Negacyclic multiplication in Z_q[X]/(X^256 + 1) by Karatsuba, independent of
+//! the NTT, for differential testing of the NTT-based product.
+
+const N: usize = 256;
+const Q: u32 = 3329;
+// Below this length the halves are multiplied by schoolbook.
+const THRESHOLD: usize = 16;
+
+/// Multiplies two polynomials given as 256 coefficients reduced mod q.
+pub fn multiply(f: &[u16], g: &[u16]) -> Result<Vec<u16>, String> {
+    if f.len() != N || g.len() != N {
+        return Err(format!("polynomials must have {} coefficients", N));
+    }
+    if f.iter().chain(g).any(|&x| x as u32 >= Q) {
+        return Err(String::from("coefficients must be reduced mod q"));
+    }
+    let f: Vec<u32> = f.iter().map(|&x| x as u32).collect();
+    let g: Vec<u32> = g.iter().map(|&x| x as u32).collect();
+    let product = karatsuba(&f, &g);
+
+    // X^256 = -1
+    Ok((0..N)
+        .map(|i| ((product[i] + Q - product[i + N]) % Q) as u16)
+        .collect())
+}
+
+// Returns the 2n coefficients of the product, reduced mod q.
+fn karatsuba(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let n = a.len();
+    if n <= THRESHOLD {
+        return schoolbook(a, b);
+    }
+
+    let h = n / 2;
+    let (a_0, a_1) = a.split_at(h);
+    let (b_0, b_1) = b.split_at(h);
+    let z_0 = karatsuba(a_0, b_0);
+    let z_2 = karatsuba(a_1, b_1);
+    let a_sum: Vec<u32> = a_0.iter().zip(a_1).map(|(x, y)| (x + y) % Q).collect();
+    let b_sum: Vec<u32> = b_0.iter().zip(b_1).map(|(x, y)| (x + y) % Q).collect();
+    let z_1 = karatsuba(&a_sum, &b_sum);
+
+    let mut product = vec![0; 2 * n];
+    for i in 0..n {
+        product[i] = (product[i] + z_0[i]) % Q;
+        product[i + h] = (product[i + h] + z_1[i] + 2 * Q - z_0[i] - z_2[i]) % Q;
+        product[i + n] = (product[i + n] + z_2[i]) % Q;
+    }
+    product
+}
+
+fn schoolbook(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut product = vec![0; 2 * a.len()];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            product[i + j] = (product[i + j] + x * y) % Q;
+        }
+    }
+    product
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::{multiply, N, Q};
+
+    fn negacyclic_schoolbook(f: &[u16], g: &[u16]) -> Vec<u16> {
+        let mut product = vec![0_i64; N];
+        for i in 0..N {
+            for j in 0..N {
+                let term = f[i] as i64 * g[j] as i64;
+                if i + j < N {
+                    product[i + j] += term;
+                } else {
+                    product[i + j - N] -= term;
+                }
+            }
+        }
+        product
+            .iter()
+            .map(|x| x.rem_euclid(Q as i64) as u16)
+            .collect()
+    }
+
+    #[test]
+    fn matches_schoolbook() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..16 {
+            let f: Vec<u16> = (0..N).map(|_| rng.gen_range(0..Q as u16)).collect();
+            let g: Vec<u16> = (0..N).map(|_| rng.gen_range(0..Q as u16)).collect();
+            assert_eq!(multiply(&f, &g).unwrap(), negacyclic_schoolbook(&f, &g));
+        }
+
+        let mut x_255 = vec![0; N];
+        x_255[255] = 1;
+        let mut x = vec![0; N];
+        x[1] = 1;
+        let mut minus_one = vec![0; N];
+        minus_one[0] = Q as u16 - 1;
+        assert_eq!(multiply(&x_255, &x).unwrap(), minus_one);
+
+        assert!(multiply(&x[..255], &x).is_err());
+        assert!(multiply(&[Q as u16; N], &x).is_err());
+    }
+}
@@ -1,48 +1,187 @@
 use num_bigint::{BigUint, ToBigInt};
 use num_traits::{One, Zero};
+#[cfg(feature = "shuffling")]
+use rand::seq::SliceRandom;
 use rand::Rng;
+use sha3::digest::XofReader;
 use std::{
+    cell::RefCell,
+    collections::HashMap,
     fmt::Debug,
-    ops::{Add, AddAssign, Mul, Sub},
+    ops::{Add, AddAssign, Index, Mul, Neg, Sub, SubAssign},
+    rc::Rc,
+    slice::Iter,
 };
 
+/// The ring Z_q[X]/(X^n + 1) and the primitive n-th root of unity `zeta` its
+/// NTT is built from. ML-KEM uses n = 256, q = 3329 and zeta = 17.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RingParams {
+    n: usize,
+    q: usize,
+    zeta: usize,
+}
+
+impl RingParams {
+    pub const ML_KEM: RingParams = RingParams {
+        n: 256,
+        q: 3329,
+        zeta: 17,
+    };
+
+    /// A miniature ring, n = 16, q = 257 and zeta = 2, with 2-byte messages
+    /// that can be enumerated exhaustively.
+    pub const TOY: RingParams = RingParams {
+        n: 16,
+        q: 257,
+        zeta: 2,
+    };
+
+    /// Checks that n is a power of two between 8 and 256, that q is a prime
+    /// below 2^12 and that zeta^(n/2) = -1 mod q, so that the incomplete NTT of
+    /// FIPS 203 carries over with n/2 - 1 zetas and degree-two base cases.
+    // `%` rather than `is_multiple_of`, which needs Rust 1.87.
+    #[allow(clippy::manual_is_multiple_of)]
+    pub fn new(n: usize, q: usize, zeta: usize) -> Result<Self, String> {
+        if !n.is_power_of_two() || !(8..=256).contains(&n) {
+            return Err(String::from("n must be a power of two between 8 and 256"));
+        }
+        if !(3..4096).contains(&q) || (2..q).take_while(|p| p * p <= q).any(|p| q % p == 0) {
+            return Err(String::from("q must be an odd prime below 4096"));
+        }
+        if !(1..q).contains(&zeta) || pow_mod(zeta, n / 2, q) != q - 1 {
+            return Err(String::from(
+                "zeta must be a primitive n-th root of unity mod q",
+            ));
+        }
+        Ok(RingParams { n, q, zeta })
+    }
+
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    pub fn q(&self) -> usize {
+        self.q
+    }
+
+    pub fn zeta(&self) -> usize {
+        self.zeta
+    }
+
+    /// The number of bits needed to encode a coefficient, 12 for ML-KEM.
+    pub fn bits(&self) -> usize {
+        (usize::BITS - (self.q - 1).leading_zeros()) as usize
+    }
+
+    /// The length of a fully encoded polynomial, 384 bytes for ML-KEM.
+    pub fn polynomial_size(&self) -> usize {
+        self.n * self.bits() / 8
+    }
+
+    /// The length of the PRF output consumed by CBD_eta, 64 eta for ML-KEM.
+    pub(crate) fn cbd_size(&self, eta: u8) -> usize {
+        eta as usize * self.n / 4
+    }
+
+    /// zeta^BitRev(i) for i < n/2, where BitRev reverses log2(n) - 1 bits.
+    /// Computed once per thread and ring.
+    fn ntt_zetas(&self) -> Rc<[usize]> {
+        thread_local! {
+            static ZETAS: RefCell<HashMap<RingParams, Rc<[usize]>>> = RefCell::new(HashMap::new());
+        }
+
+        ZETAS.with(|zetas| {
+            zetas
+                .borrow_mut()
+                .entry(*self)
+                .or_insert_with(|| {
+                    let half = self.n / 2;
+                    let shift = usize::BITS - half.trailing_zeros();
+                    (0..half)
+                        .map(|i| pow_mod(self.zeta, i.reverse_bits() >> shift, self.q))
+                        .collect()
+                })
+                .clone()
+        })
+    }
+
+    // (n/2)^-1 mod q, 3303 for ML-KEM.
+    fn ntt_f(&self) -> usize {
+        pow_mod(self.n / 2, self.q - 2, self.q)
+    }
+}
+
+impl Default for RingParams {
+    fn default() -> Self {
+        RingParams::ML_KEM
+    }
+}
+
+fn pow_mod(base: usize, exponent: usize, modulus: usize) -> usize {
+    let mut result = 1;
+    let mut base = base % modulus;
+    let mut exponent = exponent;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = result * base % modulus;
+        }
+        base = base * base % modulus;
+        exponent >>= 1;
+    }
+    result
+}
+
+/// A polynomial of R_q, in the normal domain or in the NTT domain. Products
+/// are only defined between two polynomials in the same domain.
 #[derive(Default, Clone, PartialEq)]
 pub struct Ring {
-    q: usize,
-    n: usize,
+    params: RingParams,
     coefficients: Vec<BigUint>,
-    root_of_unity: u8,
-    ntt_zetas: Vec<usize>,
-    ntt_f: usize,
     is_ntt: bool,
 }
 
 impl Ring {
-    pub fn new(coefficients: &Vec<BigUint>, is_ntt: bool) -> Self {
+    /// A polynomial of the ML-KEM ring from its coefficients, reduced mod q.
+    pub fn new(coefficients: &[BigUint], is_ntt: bool) -> Self {
+        Self::with_params(RingParams::ML_KEM, coefficients, is_ntt)
+    }
+
+    /// A polynomial of the ring `params` from its n coefficients, reduced mod q.
+    /// Panics if there are not exactly n coefficients.
+    pub fn with_params(params: RingParams, coefficients: &[BigUint], is_ntt: bool) -> Self {
+        assert_eq!(coefficients.len(), params.n, "Invalid polynomial length");
         Ring {
-            q: 3329,
-            n: 256,
-            coefficients: coefficients.clone(),
-            root_of_unity: 17,
-            ntt_f: 3303, // pow(128, -1, 3329)
-            ntt_zetas: vec![
-                1, 1729, 2580, 3289, 2642, 630, 1897, 848, 1062, 1919, 193, 797, 2786, 3260, 569,
-                1746, 296, 2447, 1339, 1476, 3046, 56, 2240, 1333, 1426, 2094, 535, 2882, 2393,
-                2879, 1974, 821, 289, 331, 3253, 1756, 1197, 2304, 2277, 2055, 650, 1977, 2513,
-                632, 2865, 33, 1320, 1915, 2319, 1435, 807, 452, 1438, 2868, 1534, 2402, 2647,
-                2617, 1481, 648, 2474, 3110, 1227, 910, 17, 2761, 583, 2649, 1637, 723, 2288, 1100,
-                1409, 2662, 3281, 233, 756, 2156, 3015, 3050, 1703, 1651, 2789, 1789, 1847, 952,
-                1461, 2687, 939, 2308, 2437, 2388, 733, 2337, 268, 641, 1584, 2298, 2037, 3220,
-                375, 2549, 2090, 1645, 1063, 319, 2773, 757, 2099, 561, 2466, 2594, 2804, 1092,
-                403, 1026, 1143, 2150, 2775, 886, 1722, 1212, 1874, 1029, 2110, 2935, 885, 2154,
-            ],
+            params,
+            coefficients: coefficients.iter().map(|x| x % params.q).collect(),
             is_ntt,
         }
     }
 
     pub fn zero() -> Self {
-        let coefficients = vec![BigUint::zero(); 256];
-        Self::new(&coefficients, false)
+        Self::zero_in_domain(RingParams::ML_KEM, false)
+    }
+
+    pub fn zero_in_domain(params: RingParams, is_ntt: bool) -> Self {
+        let coefficients = vec![BigUint::zero(); params.n];
+        Self::with_params(params, &coefficients, is_ntt)
+    }
+
+    pub fn params(&self) -> RingParams {
+        self.params
+    }
+
+    pub fn coefficients(&self) -> &[BigUint] {
+        &self.coefficients
+    }
+
+    /// Iterates over the coefficients, constant term first.
+    pub fn iter(&self) -> Iter<'_, BigUint> {
+        self.coefficients.iter()
+    }
+
+    pub fn is_ntt(&self) -> bool {
+        self.is_ntt
     }
 
     pub fn one() -> Self {
@@ -57,193 +196,315 @@ impl Ring {
         Self::new(&coefficients, false)
     }
 
+    // Results of the ring operations are already reduced, and injected faults
+    // must not be, so this skips the reduction of `with_params`.
+    fn _like(&self, coefficients: &[BigUint], is_ntt: bool) -> Self {
+        debug_assert_eq!(coefficients.len(), self.params.n);
+        Ring {
+            params: self.params,
+            coefficients: coefficients.to_vec(),
+            is_ntt,
+        }
+    }
+
+    // Whether `self` and `rhs` can be added: the same ring, the same domain and
+    // n coefficients each.
+    fn _matches(&self, rhs: &Ring) -> bool {
+        self.params == rhs.params
+            && self.is_ntt == rhs.is_ntt
+            && self.coefficients.len() == self.params.n
+            && rhs.coefficients.len() == rhs.params.n
+    }
+
+    fn _check_d(&self, d: u8) {
+        assert!(
+            (1..self.params.bits()).contains(&(d as usize)),
+            "d must be between 1 and {}",
+            self.params.bits() - 1
+        );
+    }
+
     fn add_mod_q(&self, x: &BigUint, y: &BigUint) -> BigUint {
-        (x + y) % self.q
+        (x + y) % self.params.q
     }
 
     fn sub_mod_q(&self, x: &BigUint, y: &BigUint) -> BigUint {
         let x = x.to_bigint().unwrap();
         let y = y.to_bigint().unwrap();
-        let out = (((x - y) % self.q) + self.q) % self.q;
+        let out = (((x - y) % self.params.q) + self.params.q) % self.params.q;
         out.to_biguint().unwrap()
     }
 
     pub fn random() -> Self {
-        let mut rng = rand::thread_rng();
+        Self::random_with_rng(RingParams::ML_KEM, &mut rand::thread_rng(), false)
+    }
+
+    /// Samples every coefficient uniformly from [0, q) with `rng`.
+    pub fn random_with_rng<R: Rng + ?Sized>(params: RingParams, rng: &mut R, is_ntt: bool) -> Self {
         let mut coefficients = vec![];
-        for _ in 0..256 {
-            let random_number: usize = rng.gen_range(0..=255);
+        for _ in 0..params.n {
+            let random_number: usize = rng.gen_range(0..params.q);
             coefficients.push(BigUint::from(random_number));
         }
-        Self::new(&coefficients, false)
+        Self::with_params(params, &coefficients, is_ntt)
     }
 
+    /// ByteEncode_d, packing each coefficient into d bits.
     pub fn encode(&self, d: usize) -> Vec<u8> {
+        let n = self.params.n;
         let mut t = BigUint::zero();
-        for i in 0..255 {
-            t |= &self.coefficients[256 - i - 1];
+        for i in 0..(n - 1) {
+            t |= &self.coefficients[n - i - 1];
             t <<= d
         }
         t |= &self.coefficients[0];
         let mut encoding = t.to_bytes_le();
-        encoding.resize(32 * d, 0);
+        encoding.resize(n * d / 8, 0);
         encoding
     }
 
-    pub fn decode(input_bytes: &[u8], d: usize, is_ntt: bool) -> Result<Self, String> {
-        if 256 * d != input_bytes.len() * 8 {
+    /// ByteDecode_d, reducing coefficients mod q when d is the bit length of q.
+    pub fn decode(
+        params: RingParams,
+        input_bytes: &[u8],
+        d: usize,
+        is_ntt: bool,
+    ) -> Result<Self, String> {
+        if params.n * d != input_bytes.len() * 8 {
             return Err(String::from(
                 "input bytes must be a multiple of (polynomial degree) / 8",
             ));
         }
-        let m: usize;
-        if d == 12 {
-            m = 3329
-        } else {
-            m = 1 << d;
-        }
+        let m: usize = if d == params.bits() { params.q } else { 1 << d };
 
-        let mut coefficients = vec![BigUint::zero(); 256];
+        let mut coefficients = vec![BigUint::zero(); params.n];
         let mut b_int = BigUint::from_bytes_le(input_bytes);
         let mask = BigUint::from((1 << d) - 1_usize);
-        for i in 0..256 {
-            coefficients[i] = (b_int.clone() & mask.clone()) % m;
+        for coefficient in coefficients.iter_mut() {
+            *coefficient = (b_int.clone() & mask.clone()) % m;
             b_int >>= d;
         }
-        Ok(Ring::new(&coefficients, is_ntt))
+        Ok(Ring::with_params(params, &coefficients, is_ntt))
     }
 
+    /// Compress_d, rounding each coefficient to d bits. Panics unless
+    /// 1 <= d < bits(q).
     pub fn compress(&self, d: u8) -> Self {
+        self._check_d(d);
         let mut coefficients = vec![];
         for element in self.coefficients.iter() {
-            coefficients.push(self.compress_ele(element.clone(), d));
+            let value = self.compress_ele(element.clone(), d);
+            leak(&value);
+            coefficients.push(value);
         }
-        Ring::new(&coefficients, self.is_ntt)
+        self._like(&coefficients, self.is_ntt)
     }
 
     pub fn compress_ele(&self, x: BigUint, d: u8) -> BigUint {
+        self._check_d(d);
         let t: usize = 1 << d;
-        let y = (t * x + 1664_usize) / self.q;
+        let y = (t * x + self.params.q / 2) / self.params.q;
         y % t
     }
 
+    /// Decompress_d, the approximate inverse of [`Ring::compress`]. Panics
+    /// unless 1 <= d < bits(q).
     pub fn decompress(&self, d: u8) -> Self {
+        self._check_d(d);
         let mut coefficients = vec![];
         for element in self.coefficients.iter() {
             coefficients.push(self.decompress_ele(element.clone(), d));
         }
-        Ring::new(&coefficients, self.is_ntt)
+        self._like(&coefficients, self.is_ntt)
     }
 
     pub fn decompress_ele(&self, x: BigUint, d: u8) -> BigUint {
+        self._check_d(d);
         let t: usize = 1 << (d - 1);
 
-        (self.q * x + t) >> d
+        (self.params.q * x + t) >> d
     }
 
-    pub fn ntt_sample(input_bytes: &[u8]) -> Self {
-        let mut i = 0;
+    /// SampleNTT, a uniform polynomial in the NTT domain read from `xof`.
+    //
+    // Squeezes three bytes at a time as in FIPS 203, so the rejection loop
+    // cannot run out of input. Candidates are masked to the bit length of q,
+    // which leaves the 12-bit values of ML-KEM unchanged.
+    pub fn ntt_sample<R: XofReader>(params: RingParams, xof: &mut R) -> Self {
+        let n = params.n;
+        let mask: usize = (1 << params.bits()) - 1;
         let mut j = 0;
-        let mut coefficients = vec![BigUint::zero(); 256];
-        while j < 256 {
-            let a: usize = input_bytes[i].into();
-            let b: usize = input_bytes[i + 1].into();
+        let mut coefficients = vec![BigUint::zero(); n];
+        let mut input_bytes = [0u8; 3];
+        while j < n {
+            xof.read(&mut input_bytes);
+            let a: usize = input_bytes[0].into();
+            let b: usize = input_bytes[1].into();
             let c: usize = 256 * (b % 16);
-            let d_1: usize = a + c;
-            let d: usize = input_bytes[i + 2].into();
-            let d_2 = (b / 16) + (16 * d);
+            let d_1: usize = (a + c) & mask;
+            let d: usize = input_bytes[2].into();
+            let d_2 = ((b / 16) + (16 * d)) & mask;
 
-            if d_1 < 3329 {
+            if d_1 < params.q {
                 coefficients[j] = BigUint::from(d_1);
                 j += 1
             }
 
-            if d_2 < 3329 && j < 256 {
+            if d_2 < params.q && j < n {
                 coefficients[j] = BigUint::from(d_2);
                 j += 1
             }
-
-            i += 3;
         }
-        Ring::new(&coefficients, true)
+        Ring::with_params(params, &coefficients, true)
     }
 
-    pub fn cbd(input_bytes: &[u8], eta: u8, is_ntt: bool) -> Result<Self, String> {
-        let valid_byte_len: usize = (eta * 64).into();
+    /// SamplePolyCBD_eta, a polynomial with coefficients from the centered
+    /// binomial distribution, from 64 eta bytes for ML-KEM.
+    pub fn cbd(
+        params: RingParams,
+        input_bytes: &[u8],
+        eta: u8,
+        is_ntt: bool,
+    ) -> Result<Self, String> {
+        let valid_byte_len = params.cbd_size(eta);
         if valid_byte_len != input_bytes.len() {
             return Err(String::from("Invalid byte length"));
         }
-        let mut coefficients = vec![BigUint::zero(); 256];
+        let q = params.q as i128;
+        let mut coefficients = vec![BigUint::zero(); params.n];
         let mut b_int = BigUint::from_bytes_le(input_bytes);
         let mask_1: usize = (1 << eta) - 1;
         let mask_2: usize = (1 << (2 * eta)) - 1;
-        for i in 0..256 {
+        for coefficient in coefficients.iter_mut() {
             let x = b_int.clone() & BigUint::from(mask_2);
             let a = x.clone() & BigUint::from(mask_1);
             let one_bits_in_a: i128 = a.count_ones().into();
             let b = (x >> eta) & BigUint::from(mask_1);
             let one_bits_in_b: i128 = b.count_ones().into();
             b_int >>= 2 * eta;
-            let value = (((one_bits_in_a - one_bits_in_b) % 3329) + 3329) % 3329;
-            coefficients[i] = BigUint::from(value as u128);
+            let value = (((one_bits_in_a - one_bits_in_b) % q) + q) % q;
+            *coefficient = BigUint::from(value as u128);
+            leak(coefficient);
         }
-        Ok(Ring::new(&coefficients, is_ntt))
+        Ok(Ring::with_params(params, &coefficients, is_ntt))
     }
 
-    pub fn to_ntt(&self) -> Self {
+    /// The forward NTT. The result is in the NTT domain.
+    pub fn to_ntt(&self) -> Result<Self, String> {
+        let n = self.params.n;
+        let q = self.params.q;
         let mut k = 1;
-        let mut l = 128;
+        #[cfg(feature = "fault-hardened")]
+        let mut applied = 0;
+        let mut l = n / 2;
         let mut coefficients = self.coefficients.clone();
-        let zetas = &self.ntt_zetas;
+        let zetas = self.params.ntt_zetas();
         while l >= 2 {
+            let mut butterflies = vec![];
             let mut start = 0;
-            while start < 256 {
+            while start < n {
                 let zeta = zetas[k];
                 k += 1;
                 for j in start..(start + l) {
-                    let t = zeta * &coefficients[j + l];
-                    let first = &coefficients[j] + self.q;
-                    let second = t.clone() % self.q;
-                    coefficients[j + l] = (first - second) % self.q;
-                    coefficients[j] = (&coefficients[j] + t) % self.q;
+                    butterflies.push((j, zeta));
                 }
                 start += 2 * l;
             }
+            for index in Self::_processing_order(butterflies.len()) {
+                #[cfg(test)]
+                if crate::fault::skip("ntt_butterfly") {
+                    continue;
+                }
+                let (j, zeta) = butterflies[index];
+                let t = zeta * &coefficients[j + l];
+                leak(&t);
+                let first = &coefficients[j] + q;
+                let second = t.clone() % q;
+                coefficients[j + l] = (first - second) % q;
+                leak(&coefficients[j + l]);
+                coefficients[j] = (&coefficients[j] + t) % q;
+                leak(&coefficients[j]);
+                #[cfg(feature = "fault-hardened")]
+                {
+                    applied += 1;
+                }
+            }
             l >>= 1;
         }
-        Ring::new(&coefficients, true)
+        #[cfg(feature = "fault-hardened")]
+        if k != n / 2 || applied != (n.trailing_zeros() as usize - 1) * n / 2 {
+            return Err(String::from(crate::FAULT_DETECTED));
+        }
+        Ok(self._like(&coefficients, true))
     }
 
+    /// The inverse NTT. The result is in the normal domain.
+    #[allow(clippy::wrong_self_convention)]
     pub fn from_ntt(&self) -> Self {
+        let n = self.params.n;
+        let q = self.params.q;
         let mut l = 2;
-        let l_upper = 128;
+        let l_upper = n / 2;
         let mut k = l_upper - 1;
         let mut coefficients = self.coefficients.clone();
-        let zetas = &self.ntt_zetas;
-        while l <= 128 {
+        let zetas = self.params.ntt_zetas();
+        while l <= l_upper {
+            let mut butterflies = vec![];
             let mut start = 0;
-            while start < 256 {
+            while start < n {
                 let zeta = zetas[k];
                 k -= 1;
                 for j in start..(start + l) {
-                    let t = &coefficients[j].clone();
-                    coefficients[j] = t + &coefficients[j + l];
-
-                    let a = zeta * &coefficients[j + l];
-                    let b = (zeta * t).to_bigint().unwrap();
-                    let c = a % self.q;
-                    let d = ((-b % self.q) + self.q) % self.q;
-                    let e = (c + d.to_biguint().unwrap()) % self.q;
-                    coefficients[j + l] = e;
+                    butterflies.push((j, zeta));
                 }
                 start += 2 * l;
             }
+            for index in Self::_processing_order(butterflies.len()) {
+                let (j, zeta) = butterflies[index];
+                let t = &coefficients[j].clone();
+                coefficients[j] = t + &coefficients[j + l];
+
+                let a = zeta * &coefficients[j + l];
+                let b = (zeta * t).to_bigint().unwrap();
+                let c = a % q;
+                let d = ((-b % q) + q) % q;
+                let e = (c + d.to_biguint().unwrap()) % q;
+                coefficients[j + l] = e;
+            }
             l <<= 1;
         }
-        for i in 0..256 {
-            coefficients[i] = (&coefficients[i] * self.ntt_f) % self.q
+        let f = self.params.ntt_f();
+        for coefficient in coefficients.iter_mut() {
+            *coefficient = (&*coefficient * f) % q
+        }
+        self._like(&coefficients, false)
+    }
+
+    /// Multiplies every coefficient by the scalar `c` mod q.
+    pub fn scale(&self, c: usize) -> Self {
+        let mut coefficients = vec![];
+        for element in self.coefficients.iter() {
+            coefficients.push((c * element) % self.params.q);
         }
-        Ring::new(&coefficients, false)
+        self._like(&coefficients, self.is_ntt)
+    }
+
+    // Butterflies within one NTT layer, and coefficient pairs in the base
+    // multiplication, are independent of each other, so they may be visited in
+    // any order. With the `shuffling` feature the order is randomised per call.
+    fn _processing_order(len: usize) -> Vec<usize> {
+        #[allow(unused_mut)]
+        let mut order: Vec<usize> = (0..len).collect();
+        #[cfg(feature = "shuffling")]
+        order.shuffle(&mut rand::thread_rng());
+        order
+    }
+
+    #[cfg(test)]
+    pub fn inject_fault(&self, point: &'static str) -> Self {
+        let mut coefficients = self.coefficients.clone();
+        crate::fault::coefficients(point, &mut coefficients);
+        self._like(&coefficients, self.is_ntt)
     }
 
     fn _ntt_base_mul(
@@ -254,23 +515,25 @@ impl Ring {
         b_1: &BigUint,
         zeta: usize,
     ) -> (BigUint, BigUint) {
-        let r_0 = (a_0 * b_0 + zeta * a_1 * b_1) % self.q;
-        let r_1 = (a_1 * b_0 + a_0 * b_1) % self.q;
+        let r_0 = (a_0 * b_0 + zeta * a_1 * b_1) % self.params.q;
+        let r_1 = (a_1 * b_0 + a_0 * b_1) % self.params.q;
         (r_0, r_1)
     }
 
     fn _ntt_coeff_mul(&self, f_coeffs: &[BigUint], g_coeffs: &[BigUint]) -> Vec<BigUint> {
-        let mut new_coeffs = vec![];
-        for i in 0..64 {
+        let quarter = self.params.n / 4;
+        let zetas = self.params.ntt_zetas();
+        let mut new_coeffs = vec![BigUint::zero(); self.params.n];
+        for i in Self::_processing_order(quarter) {
             let (r_0, r_1) = self._ntt_base_mul(
                 &f_coeffs[4 * i],
                 &f_coeffs[4 * i + 1],
                 &g_coeffs[4 * i],
                 &g_coeffs[4 * i + 1],
-                self.ntt_zetas[64 + i],
+                zetas[quarter + i],
             );
-            let zeta = -(self.ntt_zetas[64 + i] as i128);
-            let zeta = zeta + self.q as i128;
+            let zeta = -(zetas[quarter + i] as i128);
+            let zeta = zeta + self.params.q as i128;
             let (r_2, r_3) = self._ntt_base_mul(
                 &f_coeffs[4 * i + 2],
                 &f_coeffs[4 * i + 3],
@@ -278,34 +541,55 @@ impl Ring {
                 &g_coeffs[4 * i + 3],
                 zeta as usize,
             );
-            let values = vec![r_0, r_1, r_2, r_3];
-            new_coeffs.extend(values);
+            for value in [&r_0, &r_1, &r_2, &r_3] {
+                leak(value);
+            }
+            new_coeffs[4 * i] = r_0;
+            new_coeffs[4 * i + 1] = r_1;
+            new_coeffs[4 * i + 2] = r_2;
+            new_coeffs[4 * i + 3] = r_3;
         }
         new_coeffs
     }
 
     fn _ntt_mut(&self, rhs: &Self) -> Self {
         let new_coeffs = self._ntt_coeff_mul(&self.coefficients, &rhs.coefficients);
-        Self::new(&new_coeffs, true)
+        self._like(&new_coeffs, true)
+    }
+}
+
+#[cfg(feature = "trace")]
+impl crate::trace::Intermediate for Ring {
+    fn to_json(&self) -> serde_json::Value {
+        let coefficients: Vec<u64> = self
+            .coefficients
+            .iter()
+            .map(|x| num_traits::ToPrimitive::to_u64(x).unwrap())
+            .collect();
+        serde_json::Value::from(coefficients)
     }
 }
 
+fn leak(_value: &BigUint) {
+    #[cfg(feature = "leakage")]
+    crate::leakage::record(_value);
+}
+
 impl Add for &Ring {
     type Output = Ring;
 
     fn add(self, rhs: Self) -> Self::Output {
-        // TODO: Add checks
+        assert!(self._matches(rhs), "Invalid rings");
         let mut new_coeffs = vec![];
         for (x, y) in self.coefficients.iter().zip(rhs.coefficients.iter()) {
             new_coeffs.push(self.add_mod_q(x, y));
         }
-        Ring::new(&new_coeffs, self.is_ntt)
+        self._like(&new_coeffs, self.is_ntt)
     }
 }
 
 impl AddAssign for Ring {
     fn add_assign(&mut self, rhs: Self) {
-        // TODO: Add checks
         let new_ring = &*self + &rhs;
         *self = new_ring;
     }
@@ -315,52 +599,88 @@ impl Sub for &Ring {
     type Output = Ring;
 
     fn sub(self, rhs: Self) -> Self::Output {
-        // TODO: Add checks
+        assert!(self._matches(rhs), "Invalid rings");
         let mut new_coeffs = vec![];
         for (x, y) in self.coefficients.iter().zip(rhs.coefficients.iter()) {
             new_coeffs.push(self.sub_mod_q(x, y));
         }
-        Ring::new(&new_coeffs, self.is_ntt)
+        self._like(&new_coeffs, self.is_ntt)
+    }
+}
+
+impl SubAssign for Ring {
+    fn sub_assign(&mut self, rhs: Self) {
+        let new_ring = &*self - &rhs;
+        *self = new_ring;
     }
 }
 
+impl Neg for &Ring {
+    type Output = Ring;
+
+    fn neg(self) -> Self::Output {
+        let zero = Ring::zero_in_domain(self.params, self.is_ntt);
+        &zero - self
+    }
+}
+
+impl Index<usize> for Ring {
+    type Output = BigUint;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.coefficients[index]
+    }
+}
+
+impl<'a> IntoIterator for &'a Ring {
+    type Item = &'a BigUint;
+    type IntoIter = Iter<'a, BigUint>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.coefficients.iter()
+    }
+}
+
+// The product of two polynomials in the NTT domain is MultiplyNTTs; in the
+// normal domain it is schoolbook multiplication mod X^n + 1. Polynomials of
+// different rings or domains give an error.
 impl Mul for &Ring {
     type Output = Result<Ring, String>;
 
     fn mul(self, rhs: Self) -> Self::Output {
-        // TODO: Add checks
-        if self.is_ntt && rhs.is_ntt {
+        if !self._matches(rhs) {
+            Err(String::from("Invalid rings"))
+        } else if self.is_ntt {
             Ok(self._ntt_mut(rhs))
-        } else if !self.is_ntt && !rhs.is_ntt {
-            let mut new_coeffs = vec![BigUint::zero(); self.n];
-            let n = self.n;
+        } else {
+            let n = self.params.n;
+            let q = self.params.q;
+            let mut new_coeffs = vec![BigUint::zero(); n];
             for i in 0..n {
                 for j in 0..(n - i) {
                     new_coeffs[i + j] += &self.coefficients[i] * &rhs.coefficients[j];
-                    new_coeffs[i + j] %= self.q
+                    new_coeffs[i + j] %= q
                 }
             }
             for j in 1..n {
                 for i in (n - j)..n {
-                    new_coeffs[i + j - n] += self.q;
-                    new_coeffs[i + j - n] -=
-                        (&self.coefficients[i] * &rhs.coefficients[j]) % self.q;
-                    new_coeffs[i + j - n] %= self.q
+                    new_coeffs[i + j - n] += q;
+                    new_coeffs[i + j - n] -= (&self.coefficients[i] * &rhs.coefficients[j]) % q;
+                    new_coeffs[i + j - n] %= q
                 }
             }
-            Ok(Ring::new(&new_coeffs, self.is_ntt))
-        } else {
-            return Err(String::from("Invalid rings"));
+            Ok(self._like(&new_coeffs, self.is_ntt))
         }
     }
 }
 
 impl Debug for Ring {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let last = self.params.n - 1;
         for (i, value) in self.coefficients.iter().enumerate() {
             if !value.is_zero() {
                 if value.is_one() {
-                    if i == 255 {
+                    if i == last {
                         write!(f, "x^{}", i)?;
                     } else if i == 0 {
                         write!(f, "{} + ", value)?;
@@ -369,7 +689,7 @@ impl Debug for Ring {
                     } else {
                         write!(f, "x^{} + ", i)?;
                     }
-                } else if i == 255 {
+                } else if i == last {
                     write!(f, "{}x^{}", value, i)?;
                 } else if i == 0 {
                     write!(f, "{} + ", value)?;
@@ -386,7 +706,247 @@ impl Debug for Ring {
 
 #[cfg(test)]
 mod tests {
-    use super::Ring;
+    use num_bigint::BigUint;
+    use num_traits::{One, ToPrimitive, Zero};
+    use rand::Rng;
+
+    use super::{Ring, RingParams};
+
+    const CASES: usize = 16;
+
+    fn random_below(bound: usize, is_ntt: bool) -> Ring {
+        let mut rng = rand::thread_rng();
+        let coefficients: Vec<BigUint> = (0..256)
+            .map(|_| BigUint::from(rng.gen_range(0..bound)))
+            .collect();
+        Ring::new(&coefficients, is_ntt)
+    }
+
+    #[test]
+    fn ntt_inverse() {
+        for _ in 0..CASES {
+            let f = Ring::random();
+            assert_eq!(f.to_ntt().unwrap().from_ntt(), f);
+            let f_hat = random_below(3329, true);
+            assert_eq!(f_hat.from_ntt().to_ntt().unwrap(), f_hat);
+        }
+    }
+
+    #[test]
+    fn ntt_multiplication_matches_schoolbook() {
+        let mut x_255 = vec![BigUint::zero(); 256];
+        x_255[255] = BigUint::one();
+        let x_255 = Ring::new(&x_255, false);
+        let minus_one = &Ring::zero() - &Ring::one();
+        assert_eq!((&x_255 * &Ring::x()).unwrap(), minus_one);
+
+        for _ in 0..4 {
+            let f = Ring::random();
+            let g = Ring::random();
+            let schoolbook = (&f * &g).unwrap();
+            let ntt = (&f.to_ntt().unwrap() * &g.to_ntt().unwrap()).unwrap();
+            assert_eq!(ntt.from_ntt(), schoolbook);
+        }
+    }
+
+    #[test]
+    fn ntt_zetas_match_fips_203() {
+        let zetas = [
+            1, 1729, 2580, 3289, 2642, 630, 1897, 848, 1062, 1919, 193, 797, 2786, 3260, 569, 1746,
+            296, 2447, 1339, 1476, 3046, 56, 2240, 1333, 1426, 2094, 535, 2882, 2393, 2879, 1974,
+            821, 289, 331, 3253, 1756, 1197, 2304, 2277, 2055, 650, 1977, 2513, 632, 2865, 33,
+            1320, 1915, 2319, 1435, 807, 452, 1438, 2868, 1534, 2402, 2647, 2617, 1481, 648, 2474,
+            3110, 1227, 910, 17, 2761, 583, 2649, 1637, 723, 2288, 1100, 1409, 2662, 3281, 233,
+            756, 2156, 3015, 3050, 1703, 1651, 2789, 1789, 1847, 952, 1461, 2687, 939, 2308, 2437,
+            2388, 733, 2337, 268, 641, 1584, 2298, 2037, 3220, 375, 2549, 2090, 1645, 1063, 319,
+            2773, 757, 2099, 561, 2466, 2594, 2804, 1092, 403, 1026, 1143, 2150, 2775, 886, 1722,
+            1212, 1874, 1029, 2110, 2935, 885, 2154,
+        ];
+        assert_eq!(*RingParams::ML_KEM.ntt_zetas(), zetas);
+        assert_eq!(RingParams::ML_KEM.ntt_f(), 3303);
+        assert_eq!(RingParams::ML_KEM.bits(), 12);
+        assert_eq!(RingParams::ML_KEM.polynomial_size(), 384);
+    }
+
+    #[test]
+    fn ring_params() {
+        assert_eq!(RingParams::new(256, 3329, 17), Ok(RingParams::ML_KEM));
+        assert_eq!(RingParams::new(16, 257, 2), Ok(RingParams::TOY));
+        assert!(RingParams::new(32, 257, 136).is_ok());
+        assert!(RingParams::new(12, 257, 2).is_err());
+        assert!(RingParams::new(512, 3329, 17).is_err());
+        assert!(RingParams::new(16, 255, 2).is_err());
+        assert!(RingParams::new(16, 7681, 2).is_err());
+        // 3 generates all of Z_257^*, so its order is 256, not 16.
+        assert!(RingParams::new(16, 257, 3).is_err());
+    }
+
+    #[test]
+    fn toy_ring_ntt() {
+        let params = RingParams::TOY;
+        let random = || -> Ring {
+            let coefficients: Vec<BigUint> = (0..16)
+                .map(|_| BigUint::from(rand::thread_rng().gen_range(0..257_u32)))
+                .collect();
+            Ring::with_params(params, &coefficients, false)
+        };
+        for _ in 0..CASES {
+            let f = random();
+            let g = random();
+            assert_eq!(f.to_ntt().unwrap().from_ntt(), f);
+            let schoolbook = (&f * &g).unwrap();
+            let ntt = (&f.to_ntt().unwrap() * &g.to_ntt().unwrap()).unwrap();
+            assert_eq!(ntt.from_ntt(), schoolbook);
+            assert_eq!(ntt.params(), params);
+
+            for d in [4, 9] {
+                let f = if d == 9 {
+                    f.clone()
+                } else {
+                    f.compress(d as u8)
+                };
+                let bytes = f.encode(d);
+                assert_eq!(bytes.len(), 2 * d);
+                assert_eq!(Ring::decode(params, &bytes, d, false).unwrap(), f);
+            }
+        }
+    }
+
+    #[test]
+    fn ntt_multiplication_matches_reference() {
+        let to_u16 =
+            |f: &Ring| -> Vec<u16> { f.coefficients.iter().map(|x| x.to_u16().unwrap()).collect() };
+        for _ in 0..CASES {
+            let f = Ring::random();
+            let g = Ring::random();
+            let ntt = (&f.to_ntt().unwrap() * &g.to_ntt().unwrap()).unwrap();
+            let reference = crate::reference::multiply(&to_u16(&f), &to_u16(&g)).unwrap();
+            assert_eq!(to_u16(&ntt.from_ntt()), reference);
+        }
+    }
+
+    #[test]
+    fn ring_axioms() {
+        let zero = Ring::zero();
+        let zero_hat = zero.to_ntt().unwrap();
+        let one_hat = Ring::one().to_ntt().unwrap();
+        for _ in 0..CASES {
+            let f = Ring::random();
+            let g = Ring::random();
+            let h = Ring::random();
+            assert_eq!(&f + &zero, f);
+            assert_eq!(&f + &g, &g + &f);
+            assert_eq!(&f + &(&g + &h), &(&f + &g) + &h);
+            assert_eq!(&f - &f, zero);
+            assert_eq!(&(&zero - &f) + &f, zero);
+            assert_eq!(&(&f - &g) + &g, f);
+            let mut sum = f.clone();
+            sum += g.clone();
+            assert_eq!(sum, &f + &g);
+            sum -= g.clone();
+            assert_eq!(sum, f);
+            assert_eq!(&-&f + &f, zero);
+            assert_eq!(-&-&f, f);
+
+            let (f, g, h) = (
+                f.to_ntt().unwrap(),
+                g.to_ntt().unwrap(),
+                h.to_ntt().unwrap(),
+            );
+            let mul = |a: &Ring, b: &Ring| (a * b).unwrap();
+            assert_eq!(mul(&f, &zero_hat), zero_hat);
+            assert_eq!(mul(&f, &one_hat), f);
+            assert_eq!(mul(&f, &g), mul(&g, &f));
+            assert_eq!(mul(&f, &mul(&g, &h)), mul(&mul(&f, &g), &h));
+            assert_eq!(mul(&f, &(&g + &h)), &mul(&f, &g) + &mul(&f, &h));
+            assert!((&f * &Ring::one()).is_err());
+        }
+    }
+
+    #[test]
+    fn random_with_rng() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        for params in [RingParams::ML_KEM, RingParams::TOY] {
+            let f = Ring::random_with_rng(params, &mut StdRng::seed_from_u64(7), true);
+            let g = Ring::random_with_rng(params, &mut StdRng::seed_from_u64(7), true);
+            assert_eq!(f, g);
+            assert!(f.is_ntt());
+            assert_eq!(f.iter().count(), params.n());
+            assert!(f.iter().all(|x| x < &BigUint::from(params.q())));
+            assert_eq!(&f[1], &f.coefficients()[1]);
+            assert_eq!((&f).into_iter().nth(2), Some(&f[2]));
+        }
+
+        // Coefficients must reach the top of [0, q), not stop at a byte.
+        let mut rng = StdRng::seed_from_u64(8);
+        let f = Ring::random_with_rng(RingParams::ML_KEM, &mut rng, false);
+        assert!(f.iter().any(|x| x > &BigUint::from(3000_u32)));
+    }
+
+    #[test]
+    fn compress_error_bound() {
+        let ring = Ring::zero();
+        for d in [1, 4, 5, 10, 11] {
+            // |x - decompress(compress(x))| mod± q is at most round(q / 2^(d + 1)).
+            let bound = (3329 + (1 << d)) / (1 << (d + 1));
+            for x in 0..3329_u64 {
+                let y = ring.compress_ele(BigUint::from(x), d);
+                assert!(y < BigUint::from(1_u64 << d));
+                let x_prime = ring.decompress_ele(y, d).to_u64().unwrap();
+                let error = (x + 3329 - x_prime) % 3329;
+                assert!(error.min(3329 - error) <= bound, "d = {}, x = {}", d, x);
+            }
+        }
+        for _ in 0..CASES {
+            let f = Ring::random();
+            for d in [1, 4, 5, 10, 11] {
+                let c = f.compress(d);
+                assert_eq!(c.decompress(d).compress(d), c);
+            }
+        }
+    }
+
+    #[test]
+    fn encode_decode_round_trip() {
+        for d in 1..=12 {
+            let bound = if d == 12 { 3329 } else { 1 << d };
+            for _ in 0..CASES {
+                let f = random_below(bound, false);
+                let bytes = f.encode(d);
+                assert_eq!(bytes.len(), 32 * d);
+                assert_eq!(
+                    Ring::decode(RingParams::ML_KEM, &bytes, d, false).unwrap(),
+                    f
+                );
+            }
+            assert!(Ring::decode(RingParams::ML_KEM, &vec![0; 32 * d + 1], d, false).is_err());
+        }
+    }
+
+    #[test]
+    fn scale_by_minus_one() {
+        for _ in 0..5 {
+            let f = Ring::random();
+            assert_eq!(f.scale(3328).scale(3328), f);
+            assert_eq!(f.scale(3328), -&f);
+        }
+    }
+
+    // Without `shuffling` the order is the identity, with it a fresh random
+    // permutation on every call.
+    #[test]
+    fn processing_order() {
+        let order = Ring::_processing_order(128);
+        let mut sorted = order.clone();
+        sorted.sort();
+        assert_eq!(sorted, (0..128).collect::<Vec<_>>());
+        #[cfg(not(feature = "shuffling"))]
+        assert_eq!(order, sorted);
+        // Two identical permutations of 128 elements have probability 1/128!.
+        #[cfg(feature = "shuffling")]
+        assert_ne!(order, Ring::_processing_order(128));
+    }
 
     #[test]
     #[ignore]
@@ -427,4 +987,54 @@ mod tests {
             )
         }
     }
+
+    #[test]
+    fn construction_reduces_coefficients() {
+        let coefficients = vec![BigUint::from(3329_u32 + 5); 256];
+        let f = Ring::new(&coefficients, false);
+        assert!(f.iter().all(|x| *x == BigUint::from(5_u32)));
+        let coefficients = vec![BigUint::from(258_u32); 16];
+        let f = Ring::with_params(RingParams::TOY, &coefficients, true);
+        assert!(f.iter().all(|x| x.is_one()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid polynomial length")]
+    fn construction_checks_length() {
+        Ring::new(&[BigUint::one()], false);
+    }
+
+    #[test]
+    fn mul_checks_rings() {
+        let f = Ring::random();
+        let toy = Ring::zero_in_domain(RingParams::TOY, false);
+        assert_eq!(&f * &toy, Err(String::from("Invalid rings")));
+        assert!((&f * &f.to_ntt().unwrap()).is_err());
+        assert!((&f * &Ring::default()).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid rings")]
+    fn add_checks_domain() {
+        let f = Ring::random();
+        let _ = &f + &f.to_ntt().unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid rings")]
+    fn sub_checks_params() {
+        let _ = &Ring::random() - &Ring::zero_in_domain(RingParams::TOY, false);
+    }
+
+    #[test]
+    fn compression_checks_d() {
+        let f = Ring::random();
+        assert_eq!(f.compress(11).decompress(11).params(), f.params());
+        for d in [0, 12] {
+            assert!(std::panic::catch_unwind(|| f.compress(d)).is_err());
+            assert!(std::panic::catch_unwind(|| f.decompress(d)).is_err());
+        }
+        let toy = Ring::zero_in_domain(RingParams::TOY, false);
+        assert!(std::panic::catch_unwind(|| toy.compress(9)).is_err());
+    }
 }
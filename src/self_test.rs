@@ -0,0 +1,224 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[cfg(feature = "pairwise-test")]
+use rand::rngs::OsRng;
+use sha3::digest::XofReader;
+
+use crate::{HashBackend, MLKem, ML_KEM_768};
+
+#[cfg(not(test))]
+static ERROR_STATE: AtomicBool = AtomicBool::new(false);
+
+// Unit tests run concurrently, so each test thread has its own error state.
+// The integration test tests/error_state.rs runs against the global one.
+#[cfg(test)]
+thread_local! {
+    static ERROR_STATE: AtomicBool = const { AtomicBool::new(false) };
+}
+
+// FIPS 202 example values for the 1600-bit message of 200 bytes 0xa3, from
+// the NIST Examples with Intermediate Values (SHA3-256_1600, SHA3-512_1600,
+// SHAKE128_1600 and SHAKE256_1600), with SHAKE outputs truncated to 32 bytes.
+pub(crate) const HASH_KAT_MESSAGE: [u8; 200] = [0xa3; 200];
+pub(crate) const SHA3_256_KAT: &str =
+    "79f38adec5c20307a98ef76e8324afbfd46cfd81b22e3973c65fa1bd9de31787";
+pub(crate) const SHA3_512_KAT: &str = "e76dfad22084a8b1467fcf2ffa58361bec7628edf5f3fdc0e4805dc48caeeca81b7c13c30adf52a3659584739a2df46be589c51ca1a4a8416df6545a1ce8ba00";
+pub(crate) const SHAKE128_KAT: &str =
+    "131ab8d2b594946b9c81333f9bb6e0ce75c3b93104fa3469d3917457385da037";
+pub(crate) const SHAKE256_KAT: &str =
+    "cd8a920ed141aa0407a22d59288652e9d9f1a7ee0c1e7c1ca699424da84a904d";
+
+// XOF(rho, 1, 2) truncated to 32 bytes and PRF_2(s, 3) for rho = s = 32 bytes
+// 0xa3, computed with Python's hashlib.
+pub(crate) const XOF_KAT: &str = "81bab2b7b949971be08976f157ed2ea638c1f47d290faf8cea5e3bc002e076d2";
+pub(crate) const PRF_KAT: &str = "bbca34a331f46e1539e4e12793bc4ddbe691e36cc9a9943375119d051411a892178693281b86fd62a4552f5a7307a4fc5206462f482ae2f88641a69b7df164b745119b6f46bea13e47b75bce96aad3cf830a80ac669d115f0fd9c973f7b282c5c45bb3eb18262f93d420efac872c5bd90e3043f7c708fbb7ccaead54252c2985";
+
+// ML-KEM-768 vectors: d and z from tcId 26 of ML-KEM-keyGen-FIPS203 and m
+// from tcId 26 of ML-KEM-encapDecap-FIPS203.
+const KEYGEN_D: &str = "e34a701c4c87582f42264ee422d3c684d97611f2523efe0c998af05056d693dc";
+const KEYGEN_Z: &str = "a85768f3486bd32a01bf9a8f21ea938e648eae4e5448c34c3eb88820b159eedd";
+const KEYGEN_KAT: &str = "bd85b7b260f21ffa8a5520afe572165a54a906c13f29931e1f8a04c92e6b4286";
+const ENCAPS_M: &str = "2ce74ad291133518fe60c7df5d251b9d82add48462ff505c6e547e949e6b6bf7";
+const ENCAPS_K_KAT: &str = "54a0a9ad3725864312e321bf56593d30c3f1ba5a82f88dc21ea207139c4148ea";
+const ENCAPS_C_KAT: &str = "040927896e9024147099a2247d83e2cc1fc29cdc661f539b77a38e897349764a";
+const DECAPS_REJECTION_KAT: &str =
+    "eb9e492b0bc1d38c8955ae1d1ed771f0a6e5e0967bbbfe3c07dd7d57a0d39b90";
+
+pub const SELF_TEST_FAILED: &str = "self test failed, module is in the error state";
+
+/// Runs the known-answer self tests for the hash functions, `keygen`, `encaps`
+/// and `decaps`.
+///
+/// A failure puts the module into a permanent error state in which every
+/// `try_keygen`, `try_encaps` and `try_decaps` call returns an error.
+pub fn self_test() -> Result<(), String> {
+    run_self_test(&ML_KEM_768)
+}
+
+/// Runs the same self tests with ML-KEM-768 hashing through `backend`, to
+/// check a backend before it is used. A failure also enters the error state.
+pub fn self_test_with_backend<H: HashBackend>(backend: H) -> Result<(), String> {
+    run_self_test(&ML_KEM_768.with_backend(backend))
+}
+
+fn run_self_test<H: HashBackend>(ml_kem: &MLKem<H>) -> Result<(), String> {
+    check_state()?;
+    if let Err(error) = known_answer_tests(ml_kem) {
+        enter_error_state();
+        return Err(format!("{}: {}", SELF_TEST_FAILED, error));
+    }
+    Ok(())
+}
+
+pub(crate) fn check_state() -> Result<(), String> {
+    #[cfg(not(test))]
+    let failed = ERROR_STATE.load(Ordering::SeqCst);
+    #[cfg(test)]
+    let failed = ERROR_STATE.with(|state| state.load(Ordering::SeqCst));
+    if failed {
+        return Err(String::from(SELF_TEST_FAILED));
+    }
+    Ok(())
+}
+
+pub(crate) fn enter_error_state() {
+    #[cfg(not(test))]
+    ERROR_STATE.store(true, Ordering::SeqCst);
+    #[cfg(test)]
+    ERROR_STATE.with(|state| state.store(true, Ordering::SeqCst));
+}
+
+fn expect(name: &str, actual: &[u8], expected: &str) -> Result<(), String> {
+    if hex::encode(actual) != expected {
+        return Err(format!("{} known answer test failed", name));
+    }
+    Ok(())
+}
+
+// `ml_kem` must be ML-KEM-768, over any hash backend.
+pub(crate) fn known_answer_tests<H: HashBackend>(ml_kem: &MLKem<H>) -> Result<(), String> {
+    let (g_a, g_b) = ml_kem._g(&HASH_KAT_MESSAGE);
+    expect("SHA3-512", &[g_a, g_b].concat(), SHA3_512_KAT)?;
+    expect("SHA3-256", &ml_kem._h(&HASH_KAT_MESSAGE), SHA3_256_KAT)?;
+    expect("SHAKE256", &ml_kem._j(&HASH_KAT_MESSAGE), SHAKE256_KAT)?;
+    let mut shake128_output = [0u8; 32];
+    ml_kem
+        .hash
+        .shake128(&HASH_KAT_MESSAGE)
+        .read(&mut shake128_output);
+    expect("SHAKE128", &shake128_output, SHAKE128_KAT)?;
+    let seed = &HASH_KAT_MESSAGE[..32];
+    let mut xof_output = [0u8; 32];
+    ml_kem._xof(seed, 1, 2).read(&mut xof_output);
+    expect("XOF", &xof_output, XOF_KAT)?;
+    expect("PRF", &ml_kem._prf(128, seed, 3), PRF_KAT)?;
+
+    let d = hex::decode(KEYGEN_D).unwrap();
+    let z = hex::decode(KEYGEN_Z).unwrap();
+    let (ek, dk) = ml_kem._keygen_internal(&d, &z)?;
+    expect(
+        "keygen",
+        &ml_kem._h(&[ek.as_slice(), &dk].concat()),
+        KEYGEN_KAT,
+    )?;
+
+    let m = hex::decode(ENCAPS_M).unwrap();
+    let (k, c) = ml_kem._encaps_internal(&ek, &m)?;
+    expect("encaps", &k, ENCAPS_K_KAT)?;
+    expect("encaps", &ml_kem._h(&c), ENCAPS_C_KAT)?;
+
+    expect("decaps", &ml_kem._decaps_internal(&dk, &c)?, ENCAPS_K_KAT)?;
+    let mut c = c;
+    c[0] ^= 1;
+    expect(
+        "decaps",
+        &ml_kem._decaps_internal(&dk, &c)?,
+        DECAPS_REJECTION_KAT,
+    )?;
+
+    Ok(())
+}
+
+// The test message is drawn from the operating system rather than the caller's
+// generator, so that enabling the test does not change deterministic outputs.
+#[cfg(feature = "pairwise-test")]
+pub(crate) fn pairwise_consistency_test<H: HashBackend>(
+    ml_kem: &MLKem<H>,
+    ek: &[u8],
+    dk: &[u8],
+) -> Result<(), String> {
+    let m = MLKem::<H>::random_bytes(&mut OsRng, ml_kem.message_size())?;
+    let consistent = match ml_kem._encaps_internal(ek, &m) {
+        Ok((k, c)) => ml_kem
+            ._decaps_internal(dk, &c)
+            .is_ok_and(|k_prime| k == k_prime),
+        Err(_) => false,
+    };
+    if !consistent {
+        enter_error_state();
+        return Err(String::from("pairwise consistency test failed"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::{known_answer_tests, self_test, self_test_with_backend, SELF_TEST_FAILED};
+    use crate::{HashBackend, Sha3Backend, ML_KEM_768};
+
+    // SHA3-256 with one output bit flipped.
+    struct Broken;
+
+    impl HashBackend for Broken {
+        type Shake128Reader = <Sha3Backend as HashBackend>::Shake128Reader;
+
+        fn sha3_256(&self, input: &[u8]) -> [u8; 32] {
+            let mut output = Sha3Backend.sha3_256(input);
+            output[0] ^= 1;
+            output
+        }
+
+        fn sha3_512(&self, input: &[u8]) -> [u8; 64] {
+            Sha3Backend.sha3_512(input)
+        }
+
+        fn shake128(&self, input: &[u8]) -> Self::Shake128Reader {
+            Sha3Backend.shake128(input)
+        }
+
+        fn shake256(&self, input: &[u8], output: &mut [u8]) {
+            Sha3Backend.shake256(input, output)
+        }
+    }
+
+    #[test]
+    fn self_test_passes() {
+        known_answer_tests(&ML_KEM_768).unwrap();
+        self_test().unwrap();
+    }
+
+    #[test]
+    fn failure_is_sticky() {
+        // The error state is per thread in unit tests, so this one is
+        // discarded. tests/error_state.rs checks the process-wide state.
+        thread::spawn(|| {
+            let (ek, dk) = ML_KEM_768.keygen();
+            let (_, c) = ML_KEM_768.encaps(&ek);
+
+            let error = self_test_with_backend(Broken).unwrap_err();
+            assert!(error.starts_with(SELF_TEST_FAILED));
+
+            assert_eq!(ML_KEM_768.try_keygen().unwrap_err(), SELF_TEST_FAILED);
+            assert_eq!(ML_KEM_768.try_encaps(&ek).unwrap_err(), SELF_TEST_FAILED);
+            assert_eq!(
+                ML_KEM_768.try_decaps(&dk, &c).unwrap_err(),
+                SELF_TEST_FAILED
+            );
+            assert_eq!(self_test().unwrap_err(), SELF_TEST_FAILED);
+        })
+        .join()
+        .unwrap();
+    }
+}
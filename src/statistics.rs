@@ -0,0 +1,189 @@
+// Chi-square tests of the CBD and SampleNTT output distributions. The number of
+// polynomials drawn per test defaults to `DEFAULT_POLYNOMIALS` and can be raised
+// for longer runs through the `ML_KEM_STATISTICS_POLYNOMIALS` variable, e.g.
+//
+//     ML_KEM_STATISTICS_POLYNOMIALS=20000 cargo test --release statistics
+//
+// Seeds are fixed so that a failure is reproducible.
+
+use std::env;
+
+use sha3::digest::XofReader;
+
+use crate::{ring::Ring, RingParams, ML_KEM_768};
+
+const DEFAULT_POLYNOMIALS: usize = 256;
+const Q: usize = 3329;
+// Standard normal quantile for a false positive rate of 10^-6.
+const Z_CRITICAL: f64 = 4.753;
+
+fn polynomials() -> usize {
+    env::var("ML_KEM_STATISTICS_POLYNOMIALS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_POLYNOMIALS)
+}
+
+fn seed(i: usize) -> Vec<u8> {
+    let mut seed = (i as u64).to_le_bytes().to_vec();
+    seed.resize(32, 0);
+    seed
+}
+
+fn coefficients(f: &Ring) -> Vec<usize> {
+    f.encode(12)
+        .chunks(3)
+        .flat_map(|b| {
+            let (b_0, b_1, b_2) = (b[0] as usize, b[1] as usize, b[2] as usize);
+            [b_0 | (b_1 & 0xf) << 8, b_1 >> 4 | b_2 << 4]
+        })
+        .collect()
+}
+
+// Upper critical value of the chi-square distribution with `df` degrees of
+// freedom, by the Wilson-Hilferty approximation.
+fn critical_value(df: usize) -> f64 {
+    let df = df as f64;
+    let c = 2.0 / (9.0 * df);
+    df * (1.0 - c + Z_CRITICAL * c.sqrt()).powi(3)
+}
+
+fn chi_square(observed: &[usize], expected: &[f64]) -> f64 {
+    observed
+        .iter()
+        .zip(expected)
+        .map(|(&o, &e)| (o as f64 - e).powi(2) / e)
+        .sum()
+}
+
+fn binomial(n: u64, k: u64) -> u64 {
+    (1..=k).fold(1, |acc, i| acc * (n + 1 - i) / i)
+}
+
+fn check_cbd(eta: u8) {
+    let eta_range = eta as usize;
+    let mut observed = vec![0; 2 * eta_range + 1];
+    for i in 0..polynomials() {
+        let prf_output = ML_KEM_768._prf(64 * eta as usize, &seed(i), i as u8);
+        let f = Ring::cbd(RingParams::ML_KEM, &prf_output, eta, false).unwrap();
+        for x in coefficients(&f) {
+            let centered = if x > Q / 2 {
+                x as isize - Q as isize
+            } else {
+                x as isize
+            };
+            assert!(centered.unsigned_abs() <= eta_range);
+            observed[(centered + eta as isize) as usize] += 1;
+        }
+    }
+
+    let total: usize = observed.iter().sum();
+    let expected: Vec<f64> = (0..=2 * eta as u64)
+        .map(|k| total as f64 * binomial(2 * eta as u64, k) as f64 / 4_f64.powi(eta as i32))
+        .collect();
+    let statistic = chi_square(&observed, &expected);
+    let critical = critical_value(observed.len() - 1);
+    assert!(
+        statistic < critical,
+        "CBD_{}: chi-square {} >= {}",
+        eta,
+        statistic,
+        critical
+    );
+}
+
+fn sample_ntt() -> Vec<Vec<usize>> {
+    (0..polynomials())
+        .map(|i| {
+            let mut xof = ML_KEM_768._xof(&seed(i), i as u8, 0);
+            coefficients(&Ring::ntt_sample(RingParams::ML_KEM, &mut xof))
+        })
+        .collect()
+}
+
+#[test]
+fn cbd_2() {
+    check_cbd(2);
+}
+
+#[test]
+fn cbd_3() {
+    check_cbd(3);
+}
+
+#[test]
+fn sample_ntt_is_uniform() {
+    let mut observed = vec![0; Q];
+    for f in sample_ntt() {
+        for x in f {
+            observed[x] += 1;
+        }
+    }
+
+    let total: usize = observed.iter().sum();
+    let expected = vec![total as f64 / Q as f64; Q];
+    let statistic = chi_square(&observed, &expected);
+    let critical = critical_value(Q - 1);
+    assert!(
+        statistic < critical,
+        "SampleNTT: chi-square {} >= {}",
+        statistic,
+        critical
+    );
+}
+
+// The candidates d_1 and d_2 that SampleNTT parses from each three bytes of
+// the XOF stream, before rejection, until 256 coefficients are accepted.
+fn sample_ntt_candidates() -> Vec<(usize, usize)> {
+    let mut candidates = vec![];
+    for i in 0..polynomials() {
+        let mut xof = ML_KEM_768._xof(&seed(i), i as u8, 0);
+        let mut accepted = 0;
+        while accepted < 256 {
+            let mut b = [0_u8; 3];
+            xof.read(&mut b);
+            let (b_0, b_1, b_2) = (b[0] as usize, b[1] as usize, b[2] as usize);
+            let d_1 = b_0 | (b_1 & 0xf) << 8;
+            let d_2 = b_1 >> 4 | b_2 << 4;
+            accepted += (d_1 < Q) as usize;
+            if accepted < 256 {
+                accepted += (d_2 < Q) as usize;
+            }
+            candidates.push((d_1, d_2));
+        }
+    }
+    candidates
+}
+
+// The two 12-bit candidates parsed from the same three bytes, bucketed into a
+// 16 x 16 contingency table.
+#[test]
+fn sample_ntt_pairs_are_independent() {
+    const BUCKETS: usize = 16;
+    let mut table = vec![vec![0; BUCKETS]; BUCKETS];
+    for (d_1, d_2) in sample_ntt_candidates() {
+        table[d_1 * BUCKETS / 4096][d_2 * BUCKETS / 4096] += 1;
+    }
+
+    let total: usize = table.iter().flatten().sum();
+    let rows: Vec<usize> = table.iter().map(|row| row.iter().sum()).collect();
+    let columns: Vec<usize> = (0..BUCKETS)
+        .map(|j| table.iter().map(|row| row[j]).sum())
+        .collect();
+    let mut observed = vec![];
+    let mut expected = vec![];
+    for i in 0..BUCKETS {
+        for j in 0..BUCKETS {
+            observed.push(table[i][j]);
+            expected.push(rows[i] as f64 * columns[j] as f64 / total as f64);
+        }
+    }
+    let statistic = chi_square(&observed, &expected);
+    let critical = critical_value((BUCKETS - 1) * (BUCKETS - 1));
+    assert!(
+        statistic < critical,
+        "SampleNTT pairs: chi-square {} >= {}",
+        statistic,
+        critical
+    );
+}
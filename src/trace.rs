@@ -0,0 +1,187 @@
+//! Named intermediate values for debugging and comparing implementations.
+//!
+//! While a closure runs under [`capture`], `_k_pke_keygen`, `_k_pke_encrypt`,
+//! `_k_pke_decrypt` and `_decaps_internal` record their intermediates in the
+//! order they are computed. Byte strings are written as hex, polynomials as
+//! arrays of coefficients, vectors as arrays of polynomials and matrices as
+//! arrays of rows, so that `A_hat[i][j]` is the entry of FIPS 203.
+
+use std::cell::RefCell;
+
+use serde_json::{json, Value};
+
+thread_local! {
+    static RECORDING: RefCell<Option<Vec<Entry>>> = const { RefCell::new(None) };
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub function: &'static str,
+    pub name: &'static str,
+    pub value: Value,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Trace {
+    entries: Vec<Entry>,
+}
+
+impl Trace {
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Returns the first value recorded under `name` by `function`.
+    pub fn get(&self, function: &str, name: &str) -> Option<&Value> {
+        self.entries
+            .iter()
+            .find(|entry| entry.function == function && entry.name == name)
+            .map(|entry| &entry.value)
+    }
+
+    pub fn to_json(&self) -> Value {
+        let entries: Vec<Value> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                json!({
+                    "function": entry.function,
+                    "name": entry.name,
+                    "value": entry.value,
+                })
+            })
+            .collect();
+        Value::from(entries)
+    }
+}
+
+/// Runs `f` and returns its result together with the intermediates it recorded.
+pub fn capture<T>(f: impl FnOnce() -> T) -> (T, Trace) {
+    // Restores the enclosing capture, if any, even when `f` panics.
+    struct Restore(Option<Vec<Entry>>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            RECORDING.with(|recording| recording.replace(previous));
+        }
+    }
+
+    let previous = RECORDING.with(|recording| recording.replace(Some(vec![])));
+    let restore = Restore(previous);
+    let result = f();
+    let entries = RECORDING.with(|recording| recording.take());
+    drop(restore);
+    let trace = Trace {
+        entries: entries.unwrap_or_default(),
+    };
+    (result, trace)
+}
+
+pub(crate) trait Intermediate {
+    fn to_json(&self) -> Value;
+}
+
+impl Intermediate for [u8] {
+    fn to_json(&self) -> Value {
+        Value::String(hex::encode_upper(self))
+    }
+}
+
+impl Intermediate for Vec<u8> {
+    fn to_json(&self) -> Value {
+        self.as_slice().to_json()
+    }
+}
+
+pub(crate) fn record(function: &'static str, name: &'static str, value: &impl Intermediate) {
+    RECORDING.with(|recording| {
+        if let Some(entries) = recording.borrow_mut().as_mut() {
+            entries.push(Entry {
+                function,
+                name,
+                value: value.to_json(),
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic;
+
+    use super::{capture, record, RECORDING};
+    use crate::ML_KEM_768;
+
+    #[test]
+    fn decaps_trace() {
+        let d = [1_u8; 32];
+        let z = [2_u8; 32];
+        let (ek, dk) = ML_KEM_768._keygen_internal(&d, &z).unwrap();
+        let (k, c) = ML_KEM_768._encaps_internal(&ek, &[3; 32]).unwrap();
+
+        let (result, trace) = capture(|| ML_KEM_768.decaps(&dk, &c));
+        assert_eq!(result, k);
+        let names: Vec<&str> = trace.entries().iter().map(|entry| entry.name).collect();
+        assert_eq!(
+            names,
+            ["u", "v", "w", "m_prime", "K_bar", "y", "e_1", "e_2", "u", "v", "c_prime"]
+        );
+        assert_eq!(
+            trace
+                .get("k_pke_encrypt", "u")
+                .unwrap()
+                .as_array()
+                .unwrap()
+                .len(),
+            3
+        );
+        assert_eq!(
+            trace.get("decaps_internal", "c_prime").unwrap(),
+            &hex::encode_upper(&c)
+        );
+        assert_eq!(
+            trace
+                .get("k_pke_encrypt", "e_2")
+                .unwrap()
+                .as_array()
+                .unwrap()
+                .len(),
+            256
+        );
+
+        let (_, keygen) = capture(|| ML_KEM_768._keygen_internal(&d, &z).unwrap());
+        let a_hat = keygen.get("k_pke_keygen", "A_hat").unwrap();
+        assert_eq!(a_hat.as_array().unwrap().len(), 3);
+        assert_eq!(a_hat[0].as_array().unwrap().len(), 3);
+        let t_hat = keygen.get("k_pke_keygen", "t_hat").unwrap();
+        assert_eq!(t_hat[2][255], coefficient(&ek, 3 * 256 - 1));
+
+        let (_, outside) = capture(|| ());
+        assert!(outside.entries().is_empty());
+        assert_eq!(keygen.to_json().as_array().unwrap().len(), 6);
+    }
+
+    // The i-th 12-bit coefficient of an encoded vector.
+    fn coefficient(bytes: &[u8], i: usize) -> u64 {
+        let bits = 12 * i;
+        let word = u32::from_le_bytes([bytes[bits / 8], bytes[bits / 8 + 1], 0, 0]);
+        ((word >> (bits % 8)) & 0xfff) as u64
+    }
+
+    #[test]
+    fn capture_restores_state_on_panic() {
+        let (_, outer) = capture(|| {
+            record("outer", "a", &vec![1_u8]);
+            let result = panic::catch_unwind(|| capture(|| panic!("inner")));
+            assert!(result.is_err());
+            record("outer", "b", &vec![2_u8]);
+        });
+        assert_eq!(outer.entries().len(), 2);
+        assert!(outer.get("outer", "b").is_some());
+
+        let result = panic::catch_unwind(|| capture(|| panic!("outer")));
+        assert!(result.is_err());
+        record("outer", "c", &vec![3_u8]);
+        RECORDING.with(|recording| assert!(recording.borrow().is_none()));
+    }
+}
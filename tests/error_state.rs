@@ -0,0 +1,82 @@
+// The error state is process-wide outside unit tests, so this runs in its own
+// test binary, as a single test.
+
+use pqc_ml_kem::{
+    hazmat::{noise_profile, KPke},
+    self_test, self_test_with_backend, HashBackend, Sha3Backend, ML_KEM_768, SELF_TEST_FAILED,
+};
+
+// SHAKE128 with the first output byte flipped.
+struct Broken;
+
+struct BrokenReader(<Sha3Backend as HashBackend>::Shake128Reader, bool);
+
+impl sha3::digest::XofReader for BrokenReader {
+    fn read(&mut self, buffer: &mut [u8]) {
+        self.0.read(buffer);
+        if !self.1 && !buffer.is_empty() {
+            buffer[0] ^= 1;
+            self.1 = true;
+        }
+    }
+}
+
+impl HashBackend for Broken {
+    type Shake128Reader = BrokenReader;
+
+    fn sha3_256(&self, input: &[u8]) -> [u8; 32] {
+        Sha3Backend.sha3_256(input)
+    }
+
+    fn sha3_512(&self, input: &[u8]) -> [u8; 64] {
+        Sha3Backend.sha3_512(input)
+    }
+
+    fn shake128(&self, input: &[u8]) -> Self::Shake128Reader {
+        BrokenReader(Sha3Backend.shake128(input), false)
+    }
+
+    fn shake256(&self, input: &[u8], output: &mut [u8]) {
+        Sha3Backend.shake256(input, output)
+    }
+}
+
+#[test]
+fn failure_is_sticky_across_threads() {
+    let (ek, dk) = ML_KEM_768.keygen();
+    let (_, c) = ML_KEM_768.encaps(&ek);
+    let k_pke = KPke::new(&ML_KEM_768);
+    let (ek_pke, dk_pke) = k_pke.keygen(&[1; 32]).unwrap();
+    self_test().unwrap();
+
+    let error = self_test_with_backend(Broken).unwrap_err();
+    assert!(error.starts_with(SELF_TEST_FAILED));
+    assert!(error.contains("SHAKE128"));
+
+    // Every thread now sees the error state.
+    std::thread::spawn(move || {
+        assert_eq!(ML_KEM_768.try_keygen().unwrap_err(), SELF_TEST_FAILED);
+        assert_eq!(ML_KEM_768.try_encaps(&ek).unwrap_err(), SELF_TEST_FAILED);
+        assert_eq!(
+            ML_KEM_768.try_decaps(&dk, &c).unwrap_err(),
+            SELF_TEST_FAILED
+        );
+        assert_eq!(self_test().unwrap_err(), SELF_TEST_FAILED);
+        assert_eq!(
+            self_test_with_backend(Sha3Backend).unwrap_err(),
+            SELF_TEST_FAILED
+        );
+        assert!(matches!(k_pke.keygen(&[1; 32]), Err(e) if e == SELF_TEST_FAILED));
+        assert_eq!(
+            k_pke.encrypt(&ek_pke, &[0; 32], &[0; 32]).unwrap_err(),
+            SELF_TEST_FAILED
+        );
+        assert_eq!(k_pke.decrypt(&dk_pke, &c).unwrap_err(), SELF_TEST_FAILED);
+        assert_eq!(
+            noise_profile(&ML_KEM_768, &dk, &c).unwrap_err(),
+            SELF_TEST_FAILED
+        );
+    })
+    .join()
+    .unwrap();
+}